@@ -0,0 +1,29 @@
+# prose from an mdBook print.html: text of <p> and <li> inside <main>, without <pre> blocks or sentences containing inline <code>
+import sys, html.parser, re
+class P(html.parser.HTMLParser):
+    def __init__(s):
+        super().__init__(); s.main=0; s.skip=0; s.block=0; s.buf=[]; s.out=[]
+    def handle_starttag(s,t,a):
+        if t=="main": s.main+=1
+        if t=="code" and s.block and not s.skip: s.buf.append("\0")
+        if t in("pre","code","script","style","nav","table","sup"): s.skip+=1
+        if t in("p","li"):
+            if s.block and s.buf: s.flush()
+            s.block+=1
+    def handle_endtag(s,t):
+        if t=="main": s.main-=1
+        if t in("pre","code","script","style","nav","table","sup"): s.skip-=1
+        if t in("p","li") and s.block:
+            s.block-=1; s.flush()
+    def handle_data(s,d):
+        if s.main and s.block and not s.skip: s.buf.append(d)
+    def flush(s):
+        t=re.sub(r"\s+"," ","".join(s.buf)).strip(); s.buf=[]
+        for a,b in (("\u2019","'"),("\u2018","'"),("\u201c",'"'),("\u201d",'"'),("\u2014"," - "),("\u2013","-"),("\u2026","..."),("\u00a0"," ")):
+            t=t.replace(a,b)
+        # sentences that contained inline code would have holes in them
+        sentences=[x for x in re.split(r"(?<=[.!?])\s+",t) if "\0" not in x and x.isascii()]
+        t=re.sub(r"  +"," "," ".join(sentences)).strip()
+        if len(t)>=40: s.out.append(t)
+p=P(); p.feed(open(sys.argv[1],encoding="utf-8").read())
+sys.stdout.write("\n".join(p.out)+"\n")
@@ -0,0 +1,23 @@
+# Builds a preset table of character n-grams (lengths 1 to 4, counted line by line) in the csv format read by alc.
+# usage: python3 generate.py <english|code> <min count for 2-grams and longer> <text files...> > <name>.csv
+import sys, collections
+def table(lines, min_count):
+    counts = collections.Counter()
+    lines = [l for l in lines if l.isascii()] if kind == "code" else lines
+    for line in lines:
+        for n in range(1, 5):
+            for i in range(len(line) - n + 1):
+                counts[line[i:i+n]] += 1
+    rows = sorted(((g, c) for g, c in counts.items() if (c >= min_count or len(g) == 1) and "\t" not in g), key=lambda x: (len(x[0]), -x[1], x[0]))
+    out = ["ngram,count"]
+    for g, c in rows:
+        if ',' in g or '"' in g:
+            g = '"' + g.replace('"', '""') + '"'
+        out.append(f"{g},{c}")
+    return "\n".join(out) + "\n"
+kind, min_count, *files = sys.argv[1:]
+lines = []
+for f in files:
+    for line in open(f, encoding='utf-8').read().splitlines():
+        lines.append(line.lstrip() if kind == 'code' else line)
+sys.stdout.write(table(lines, int(min_count)))
@@ -20,6 +20,8 @@ pub enum AlcError {
 	RegexError(#[from] regex::Error),
 	#[error(transparent)]
 	TomlError(#[from] toml::de::Error),
+	#[error(transparent)]
+	GlobError(#[from] globset::Error),
 
 	#[error("{0} cannot be parsed into a KeycodeKey, {1}")]
 	InvalidKeycodeKeyFromString(String, String), // second param tries to describe what is invalid
@@ -45,14 +47,49 @@ pub enum AlcError {
 	LayoutLayerSwitchError(Vec<(LayoutPosition, LayoutPosition)>),
 	#[error("symmetric keys are disjointed: {0:?}")]
 	LayoutSymmetryError(Vec<(LayoutPosition, LayoutPosition)>),
+	#[error("combo {0} is invalid, {1}")]
+	InvalidComboError(String, String),
+	#[error("magic rule {0} is invalid, {1}")]
+	InvalidMagicRuleError(String, String),
+	#[error("region {0} needs at least two moveable keys for its keys to be able to swap, use a non-moveable key instead")]
+	LayoutRegionError(u8),
 
 	#[error("expected {0} to be a directory")]
 	ExpectedDirectoryError(PathBuf),
 
+	#[error("could not read n-gram table {0:?}, {1}")]
+	NgramTableError(PathBuf, String),
+
+	#[error("could not read keystroke log {0:?}, {1}")]
+	KeystrokeLogError(PathBuf, String),
+
+	#[error("dataset preset {0} does not exist, available presets are {1}")]
+	DatasetPresetError(String, String),
+	#[error("dataset preset {0} only has n-grams up to length {1}, so max_ngram_size can be at most {1} but is {2}")]
+	DatasetPresetNgramSizeError(String, usize, usize),
+
 	#[error("ngram {0} cannot be typed on the layout, check to make sure all keycodes are present in the layout or that the relevant options are checked")]
 	UntypeableNgramError(Ngram),
 	#[error("the number of dataset weights {0} must match the number of datasets {1}")]
 	DatasetWeightsMismatchError(usize, usize),
+	#[error("the number of n-gram size weights {0} must match the max n-gram size {1}")]
+	NgramSizeWeightsMismatchError(usize, usize),
+	#[error("n-gram size weights can't be negative, but found {0}")]
+	NegativeNgramSizeWeightError(f64),
+	#[error("at least one n-gram size weight must be greater than 0")]
+	ZeroNgramSizeWeightsError,
+	#[error("dataset {0} only has n-gram sizes whose n-gram size weights are 0")]
+	DatasetZeroNgramSizeWeightsError(String),
+	#[error("the number of n-gram coverages {0} must be 1 or match the max n-gram size {1}")]
+	NgramCoverageMismatchError(usize, usize),
+	#[error("n-gram coverage must be greater than 0 and at most 1, but found {0}")]
+	NgramCoverageError(f64),
+
+	#[error("constraint {0} is violated: {1}")]
+	ConstraintViolationError(String, String),
+
+	#[error("island count must be between 1 and the population size {1}, but found {0}")]
+	IslandCountError(usize, u32),
 
 	#[error("could not find valid swap after {0} tries, {1}")]
 	SwapFallbackError(u32, String),
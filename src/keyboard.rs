@@ -1,10 +1,12 @@
 use std::ops::Index;
 use std::fmt;
 
+pub mod combo;
 pub mod key;
 pub mod layer;
 pub mod layout;
 pub mod layout_presets;
+pub mod magic;
 
 
 /// Describes position in a keyboard layout (i.e., a collection of layers). For a single layer, layer_index is ignored.
@@ -31,25 +33,54 @@ impl fmt::Display for LayoutPosition {
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd, Ord)]
 pub struct LayoutPositionSequence {
-	sequence: Vec<LayoutPosition>
+	sequence: Vec<LayoutPosition>,
+	combos: Vec<(usize, usize)>, // start and end (not inclusive) indices of the positions that are pressed together as a combo
+	holds: Vec<usize>, // indices of mod-tap positions that are held for their modifier rather than tapped
 }
 impl LayoutPositionSequence {
 	pub fn push(&mut self, lp: LayoutPosition) {
 		self.sequence.push(lp)
 	}
+	/// Pushes positions that are pressed at the same time rather than one after the other
+	pub fn push_combo(&mut self, lps: &[LayoutPosition]) {
+		let start = self.sequence.len();
+		self.sequence.extend_from_slice(lps);
+		self.combos.push((start, self.sequence.len()));
+	}
+	/// Pushes a mod-tap position that is held for its modifier rather than tapped
+	pub fn push_hold(&mut self, lp: LayoutPosition) {
+		self.holds.push(self.sequence.len());
+		self.sequence.push(lp);
+	}
 	pub fn from_vector(lps: Vec<LayoutPosition>) -> Self {
-		LayoutPositionSequence { sequence: lps }
+		LayoutPositionSequence { sequence: lps, combos: vec![], holds: vec![] }
 	}
 	pub fn from_tuples(lps: Vec<(usize, usize, usize)>) -> Self {
 		let lps_vec = lps.into_iter().map(LayoutPosition::from_tuple).collect();
-		LayoutPositionSequence { sequence: lps_vec }
+		LayoutPositionSequence { sequence: lps_vec, combos: vec![], holds: vec![] }
 	}
 	pub fn append(&mut self, other: &mut Self) {
+		let offset = self.sequence.len();
+		self.combos.extend(other.combos.drain(..).map(|(start, end)| (start + offset, end + offset)));
+		self.holds.extend(other.holds.drain(..).map(|i| i + offset));
 		self.sequence.append(&mut other.sequence)
 	}
+	pub fn combos(&self) -> &[(usize, usize)] {
+		&self.combos
+	}
+	pub fn holds(&self) -> &[usize] {
+		&self.holds
+	}
+	/// Number of presses needed for the sequence, where each combo counts as a single press
+	pub fn press_count(&self) -> usize {
+		self.sequence.len() - self.combos.iter().map(|(start, end)| end - start - 1).sum::<usize>()
+	}
 	pub fn last(&self) -> Option<&LayoutPosition> {
 		self.sequence.last()
 	}
+	pub fn iter(&self) -> std::slice::Iter<'_, LayoutPosition> {
+		self.sequence.iter()
+	}
 	pub fn len(&self) -> usize {
 		self.sequence.len()
 	}
@@ -77,6 +108,8 @@ impl fmt::Display for LayoutPositionSequence {
 		for (i, lp) in self.sequence.clone().iter().enumerate() {
 			if i == self.sequence.len() - 1 {
 				write!(f, "{}", lp)?;
+			} else if self.combos.iter().any(|(start, end)| i >= *start && i + 1 < *end) {
+				write!(f, "{} + ", lp)?;
 			} else {
 				write!(f, "{} -> ", lp)?;
 			}
@@ -0,0 +1,78 @@
+use std::fmt;
+
+use crate::alc_error::AlcError;
+use crate::text_processor::keycode::Keycode::{self, *};
+use super::key::{KeyValue, KeycodeKey};
+use super::LayoutPosition;
+
+/// A combo (or chord) types its keycode when all of its positions are pressed at the same time. The combo key uses the usual {keycode}_{moveability flag}{symmetry flag} format; a moveable combo lets the optimizer choose which keycode goes on it, while the positions themselves stay fixed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Combo {
+	positions: Vec<LayoutPosition>,
+	key: KeycodeKey,
+}
+impl Combo {
+	pub fn try_new(positions: Vec<LayoutPosition>, key: KeycodeKey) -> Result<Self, AlcError> {
+		let combo_string = format!("{:?} -> {}", positions, format!("{:b}", key).trim());
+		if positions.len() < 2 {
+			return Err(AlcError::InvalidComboError(combo_string, String::from("combos need at least two positions")));
+		}
+		for (i, lp) in positions.iter().enumerate() {
+			if positions[..i].contains(lp) {
+				return Err(AlcError::InvalidComboError(combo_string, format!("{} is repeated", lp)));
+			}
+			if lp.layer_index != positions[0].layer_index {
+				return Err(AlcError::InvalidComboError(combo_string, String::from("all positions must be on the same layer")));
+			}
+		}
+		if key.is_symmetric() || key.region().is_some() || key.hold().is_some() {
+			return Err(AlcError::InvalidComboError(combo_string, String::from("combo keys can't be symmetric, in a region or mod-tap")));
+		}
+		if key.value().is_layer_switch() || matches!(key.value(), _LST(_, _)) {
+			return Err(AlcError::InvalidComboError(combo_string, String::from("combos can't switch layers")));
+		}
+		Ok(Combo { positions, key })
+	}
+	pub fn positions(&self) -> &[LayoutPosition] {
+		&self.positions
+	}
+	pub fn key(&self) -> &KeycodeKey {
+		&self.key
+	}
+	pub fn set_value(&mut self, new_value: Keycode) {
+		self.key.set_value(new_value)
+	}
+	pub fn layer_index(&self) -> usize {
+		self.positions[0].layer_index
+	}
+}
+impl KeyValue for Combo {
+	type Item = Keycode;
+	fn value(&self) -> Self::Item {
+		self.key.value()
+	}
+}
+impl fmt::Display for Combo {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let positions_string = self.positions.iter().map(|lp| lp.to_string()).collect::<Vec<String>>().join(" + ");
+		write!(f, "{} -> {}", positions_string, self.key.to_string().trim())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_combo_validation() {
+		let key = KeycodeKey::try_from("TAB_10").unwrap();
+		let combo = Combo::try_new(vec![LayoutPosition::new(0, 1, 1), LayoutPosition::new(0, 1, 2)], key).unwrap();
+		assert_eq!(combo.value(), _TAB);
+		assert_eq!(combo.to_string(), "(L0 R1 C1) + (L0 R1 C2) -> TAB");
+		assert!(Combo::try_new(vec![LayoutPosition::new(0, 1, 1)], key).is_err());
+		assert!(Combo::try_new(vec![LayoutPosition::new(0, 1, 1), LayoutPosition::new(0, 1, 1)], key).is_err());
+		assert!(Combo::try_new(vec![LayoutPosition::new(0, 1, 1), LayoutPosition::new(1, 1, 2)], key).is_err());
+		assert!(Combo::try_new(vec![LayoutPosition::new(0, 1, 1), LayoutPosition::new(0, 1, 2)], KeycodeKey::try_from("LS1_10").unwrap()).is_err());
+	}
+}
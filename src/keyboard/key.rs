@@ -19,10 +19,16 @@ pub struct KeycodeKey {
 	value: Keycode,
 	is_moveable: bool,
 	is_symmetric: bool,
+	/// Keys in a region only ever swap with other keys in the same region, so a keycode placed in a region stays within the positions of that region
+	region: Option<u8>,
+	/// What a layer-tap key (_LTAP) types when tapped instead of held
+	tap: Option<Keycode>,
+	/// Modifier of a mod-tap key, which types its value when tapped and acts as the modifier when held. Unlike the other fields, the hold belongs to the position rather than the keycode, see [crate::keyboard::layout::Layout::swap]
+	hold: Option<Keycode>,
 }
 impl KeycodeKey {
 	pub fn default_from_keycode(k: Keycode) -> Self {
-		Self { value: k, is_moveable: true, is_symmetric: false }
+		Self { value: k, is_moveable: true, is_symmetric: false, region: None, tap: None, hold: None }
 	}
 	pub fn set_value(&mut self, new_value: Keycode) {
 		self.value = new_value
@@ -39,50 +45,87 @@ impl KeycodeKey {
 	pub fn set_is_symmetric(&mut self, new_symmetric: bool) {
 		self.is_symmetric = new_symmetric
 	}
+	pub fn region(&self) -> Option<u8> {
+		self.region
+	}
+	pub fn set_region(&mut self, new_region: Option<u8>) {
+		self.region = new_region
+	}
+	/// Only layer-tap keys have a tap keycode
+	pub fn tap(&self) -> Option<Keycode> {
+		match self.value {
+			_LTAP(_i) => self.tap,
+			_ => None,
+		}
+	}
+	pub fn set_tap(&mut self, new_tap: Option<Keycode>) {
+		self.tap = new_tap
+	}
+	pub fn hold(&self) -> Option<Keycode> {
+		self.hold
+	}
+	pub fn set_hold(&mut self, new_hold: Option<Keycode>) {
+		self.hold = new_hold
+	}
 	pub fn replace_with(&mut self, new_key: &KeycodeKey) {
 		self.set_value(new_key.value());
 		self.set_is_moveable(new_key.is_moveable());
 		self.set_is_symmetric(new_key.is_symmetric());
+		self.set_region(new_key.region());
+		self.set_tap(new_key.tap);
+		self.set_hold(new_key.hold());
 
 	}
 }
 impl TryFrom<&str> for KeycodeKey {
 	type Error = AlcError;
-	fn try_from(key_string: &str) -> Result<Self, Self::Error> {
+	fn try_from(key_string_with_hold: &str) -> Result<Self, Self::Error> {
 		let mut key = KeycodeKey::default_from_keycode(_NO);
+		// mod-tap keys are written as {keycode}/{modifier}_{flags}, e.g., A/SFT_10 or _/SFT_10 for a blank key, so take out the modifier and parse the rest as usual
+		let key_string_without_hold: String;
+		let key_string = match key_string_with_hold.find('/') {
+			Some(slash_index) => {
+				let hold_end = key_string_with_hold[slash_index..].find('_').map_or(key_string_with_hold.len(), |i| slash_index + i);
+				let hold_string = &key_string_with_hold[slash_index + 1..hold_end];
+				let hold = Keycode::try_from(format!("_{hold_string}").as_str())?;
+				if ![_SFT, _CTRL, _ALT, _GUI].contains(&hold) {
+					return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string_with_hold), String::from("the hold of a mod-tap key must be one of SFT, CTRL, ALT or GUI")));
+				}
+				key.set_hold(Some(hold));
+				let key_value_string = &key_string_with_hold[..slash_index];
+				key_string_without_hold = format!("{}{}", key_value_string, &key_string_with_hold[hold_end..]);
+				key_string_without_hold.as_str()
+			},
+			None => key_string_with_hold,
+		};
 		let mut key_details = key_string.split('_');
 		// should check to make sure the string can actually be sliced
 		// _NO displays as _ for less clutter, so account for that
-		if &key_string[0..1] == "_" {
+		if key_string.starts_with('_') {
 			key_details.next();
 			key_details.next();
-		} else if &key_string[0..3] == "LST" {
+		} else if key_string.starts_with("LST") {
 			// this shouldn't get run since LST shouldn't appear in the layout string
 			let layer_target = &key_string[3..4].parse::<usize>()?;
 			let layer_source = &key_string[5..6].parse::<usize>()?;
 			key.set_value(_LST(*layer_target, *layer_source));
 			key_details.next();
 			key_details.next();
-		} else if &key_string[0..2] == "LS" {
-			let ls_string = key_details.next().unwrap();
-			let parsed_layer = &ls_string[2..].parse::<usize>();
-			let layer_target = match parsed_layer {
-				Ok(v) => v,
-				Err(e) => return Err(AlcError::ParseIntError(e.clone(), format!("tried to find the target layer of {} (i.e., the X in LSX)", ls_string))),
-			};
-			key.set_value(_LS(*layer_target));
-			
+		} else if let Some((layer_keycode, tap)) = layer_key_from_string(key_string.split('_').next().unwrap())? {
+			key_details.next();
+			key.set_value(layer_keycode);
+			key.set_tap(tap);
 		} else if let Some(key_value_string) = key_details.next() {
-			let key_value = Keycode::try_from(format!("_{key_value_string}").as_str())?;
+			let key_value = Keycode::try_from_display_name(key_value_string)?;
 			key.set_value(key_value);
 		} else {
 			return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("keycode not found")));
 		}
 		
 		if let Some(flags) = key_details.next() {
-			// is_moveable flag and is_symmetric flag
-			if flags.len() != 2 {
-				return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("expected two bit flags")));	
+			// is_moveable flag and is_symmetric flag, optionally followed by a region number where 0 means no region
+			if flags.len() != 2 && flags.len() != 3 {
+				return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("expected two bit flags and an optional region number")));	
 			}
 			let mut flags_iter = flags.chars();
 			// .next() is guaranteed(?) to work here since we check that there are 2 flags
@@ -91,13 +134,18 @@ impl TryFrom<&str> for KeycodeKey {
 			key.set_is_moveable(move_flag);
 			let symm_flag: bool = flags_iter.next().unwrap().to_digit(10).unwrap() != 0;
 			key.set_is_symmetric(symm_flag);
-
 			if symm_flag {
-				if let _LS(_layer_num) = key.value() {
-					return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("don't set a layer switch key to be symmetric due to the additional complexity; this may change in the future")));
-				}
 				if let _LST(_l1, _l2) = key.value() {
-					return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("don't set a layer switch key to be symmetric due to the additional complexity; this may change in the future")));
+					return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("the LST counterpart of a layer switch moves along with the layer switch, so it can't be symmetric on its own")));
+				}
+			}
+			if let Some(region_char) = flags_iter.next() {
+				let region = match region_char.to_digit(10) {
+					Some(v) => v as u8,
+					None => return Err(AlcError::InvalidKeycodeKeyFromString(String::from(key_string), String::from("region must be a single digit"))),
+				};
+				if region != 0 {
+					key.set_region(Some(region));
 				}
 			}
 
@@ -107,12 +155,52 @@ impl TryFrom<&str> for KeycodeKey {
 		Ok(key)
 	}
 }
+/// LSX, TGX, OSLX and LTX:{tap keycode} (e.g., LT1:SPC) where X is the target layer. None if the string isn't a layer key, e.g., LT on its own is the < keycode
+fn layer_key_from_string(layer_key_string: &str) -> Result<Option<(Keycode, Option<Keycode>)>, AlcError> {
+	let (layer_string, tap_string) = match layer_key_string.split_once(':') {
+		Some((l, t)) => (l, Some(t)),
+		None => (layer_key_string, None),
+	};
+	let prefix_len = layer_string.find(|c: char| c.is_ascii_digit()).unwrap_or(layer_string.len());
+	let (prefix, layer_num_string) = layer_string.split_at(prefix_len);
+	let layer_keycode: fn(usize) -> Keycode = match prefix {
+		"LS" => _LS,
+		"TG" => _TG,
+		"OSL" => _OSL,
+		"LT" => _LTAP,
+		_ => return Ok(None),
+	};
+	if layer_num_string.is_empty() {
+		return Ok(None);
+	}
+	let layer_target = match layer_num_string.parse::<usize>() {
+		Ok(v) => v,
+		Err(e) => return Err(AlcError::ParseIntError(e, format!("tried to find the target layer of {} (i.e., the X in {}X)", layer_string, prefix))),
+	};
+	let tap = match tap_string {
+		Some(t) => {
+			if prefix != "LT" {
+				return Err(AlcError::InvalidKeycodeKeyFromString(String::from(layer_key_string), String::from("only layer-tap keys (LTX:{keycode}) can have a tap keycode")));
+			}
+			let tap_keycode = Keycode::try_from_display_name(t)?;
+			if tap_keycode.is_layer_switch() || tap_keycode == _NO {
+				return Err(AlcError::InvalidKeycodeKeyFromString(String::from(layer_key_string), String::from("the tap keycode of a layer-tap key must be a regular keycode")));
+			}
+			Some(tap_keycode)
+		},
+		None => None,
+	};
+	Ok(Some((layer_keycode(layer_target), tap)))
+}
 impl Default for KeycodeKey {
 	fn default() -> Self {
 		Self {
 			value: _E,
 			is_moveable: true,
 			is_symmetric: false,
+			region: None,
+			tap: None,
+			hold: None,
 		}
 	}
 }
@@ -122,41 +210,52 @@ impl KeyValue for KeycodeKey {
 		self.value
 	}
 }
-/// {Keycode}_{Moveability}{Symmetry}
+/// {Keycode}_{Moveability}{Symmetry}{Region}, where the region is left out for keys not in a region
 /// Keycodes have an up to 4 character representation in QMK, so {:>4} pads that (we ignore the KC_). Fix magic number later
-impl fmt::Display for KeycodeKey {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let str_to_display = str::replace(&self.value.to_string(), "_", "");
+impl KeycodeKey {
+	fn value_to_display(&self) -> String {
+		let str_to_display = self.value.display_name();
 		let value_to_display = match self.value {
 			_NO => "_".to_string(),
 			_LS(i) => format!("LS{}", i),
 			_LST(_i, _j) => "_".to_string(), //format!("LST{}_{}", i, j),
+			_TG(i) => format!("TG{}", i),
+			_OSL(i) => format!("OSL{}", i),
+			_LTAP(i) => match self.tap() {
+				Some(t) => format!("LT{}:{}", i, t.display_name()),
+				None => format!("LT{}", i),
+			},
 			_ => str_to_display,
 		};
-		write!(f, "{:>4}", value_to_display)
+		match self.hold {
+			Some(h) => format!("{}/{}", value_to_display, str::replace(&h.to_string(), "_", "")),
+			None => value_to_display,
+		}
+	}
+}
+impl fmt::Display for KeycodeKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:>4}", self.value_to_display())
     }
 }
 impl fmt::Binary for KeycodeKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let m: u8 = self.is_moveable.into();
 		let s: u8 = self.is_symmetric.into();
-
-		let str_to_display = str::replace(&self.value.to_string(), "_", "");
-		let value_to_display = match self.value {
-			_NO => "_".to_string(),
-			_LS(i) => format!("LS{}", i),
-			_LST(_i, _j) => "_".to_string(), //format!("LST{}_{}", i, j),
-			_ => str_to_display,
-		};
-        write!(f, "{:>4}_{}{}", value_to_display, m, s)
+		let value_to_display = self.value_to_display();
+        match self.region {
+			Some(r) => write!(f, "{:>4}_{}{}{}", value_to_display, m, s, r),
+			None => write!(f, "{:>4}_{}{}", value_to_display, m, s),
+		}
     }
 }
 impl Randomizeable for KeycodeKey {
 	fn is_randomizeable(&self) -> bool {
-		match self.value {
-			_LS(_i) => return false,
-			_LST(_i, _j) => return false,
-			_ => (),
+		if self.value.is_layer_switch() {
+			return false;
+		}
+		if let _LST(_i, _j) = self.value {
+			return false;
 		}
 		match self.is_moveable {
 			true => (),
@@ -188,13 +287,13 @@ impl KeyValue for PhysicalKey {
 	}
 }
 
-#[derive(Debug, PartialEq, Clone, Copy, strum_macros::EnumString, strum_macros::Display)]
+#[derive(Debug, PartialEq, Clone, Copy, strum_macros::EnumString, strum_macros::Display, Serialize, Deserialize)]
 pub enum Hand {
 	Left,
 	Right,
 	PlaceholderHand,
 }
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
 use Hand::*;
 
 /// depending on your keyboard, you may be able to press the bottom left / bottom right corner key with the upper palm / joint of your pinkie finger, hence Joint
@@ -294,6 +393,7 @@ impl fmt::Display for PhalanxKey {
 #[cfg(test)]
 mod tests {
 
+	use crate::keyboard::layer::Layer;
 	use super::*;
 
 	#[test]
@@ -310,6 +410,68 @@ mod tests {
 		assert_eq!(b.is_symmetric, false);
 	}
 
+	#[test]
+	fn test_layer_keys() {
+		assert_eq!(KeycodeKey::try_from("LS2_10").unwrap().value(), _LS(2));
+		assert_eq!(KeycodeKey::try_from("TG1_10").unwrap().value(), _TG(1));
+		assert_eq!(KeycodeKey::try_from("OSL3_11").unwrap().value(), _OSL(3));
+		let k = KeycodeKey::try_from("LT1:SPC_10").unwrap();
+		assert_eq!(k.value(), _LTAP(1));
+		assert_eq!(k.tap(), Some(_SPC));
+		assert_eq!(format!("{:b}", k), "LT1:SPC_10");
+		let k = KeycodeKey::try_from("LT1_10").unwrap();
+		assert_eq!(k.tap(), None);
+		// LT without a layer is the < keycode
+		assert_eq!(KeycodeKey::try_from("LT_10").unwrap().value(), _LT);
+		assert!(KeycodeKey::try_from("TG1:SPC_10").is_err());
+		assert!(KeycodeKey::try_from("LT1:LS2_10").is_err());
+	}
+
+	#[test]
+	fn test_mod_tap() {
+		let k = KeycodeKey::try_from("A/SFT_10").unwrap();
+		assert_eq!(k.value(), _A);
+		assert_eq!(k.hold(), Some(_SFT));
+		assert!(k.is_moveable());
+		assert_eq!(format!("{:b}", k), "A/SFT_10");
+		let k = KeycodeKey::try_from("_/CTRL_101").unwrap();
+		assert_eq!(k.value(), _NO);
+		assert_eq!(k.hold(), Some(_CTRL));
+		assert_eq!(k.region(), Some(1));
+		assert_eq!(format!("{:b}", k), "_/CTRL_101");
+		assert!(KeycodeKey::try_from("A/B_10").is_err());
+	}
+
+	#[test]
+	fn test_character_keys() {
+		let k = KeycodeKey::try_from("ä_10").unwrap();
+		assert_eq!(k.value(), _UC('ä'));
+		assert_eq!(format!("{:b}", k), "   ä_10");
+		let k = KeycodeKey::try_from("DK´_00").unwrap();
+		assert_eq!(k.value(), _DEAD('´'));
+		assert!(!k.is_moveable());
+		assert_eq!(KeycodeKey::try_from("ALTGR_10").unwrap().value(), _ALTGR);
+		assert_eq!(KeycodeKey::try_from("LT1:é_10").unwrap().tap(), Some(_UC('é')));
+		let layer = Layer::<1, 3, KeycodeKey>::try_from("ä_10 DK´_10 ALTGR_10").unwrap();
+		assert_eq!(layer[(0, 0)].value(), _UC('ä'));
+	}
+
+	#[test]
+	fn test_region_flag() {
+		let k = KeycodeKey::try_from("E_102").unwrap();
+		assert_eq!(k.value(), _E);
+		assert_eq!(k.region(), Some(2));
+		assert_eq!(format!("{:b}", k), "   E_102");
+		let k = KeycodeKey::try_from("E_100").unwrap();
+		assert_eq!(k.region(), None);
+		assert_eq!(format!("{:b}", k), "   E_10");
+		let k = KeycodeKey::try_from("__101").unwrap();
+		assert_eq!(k.value(), _NO);
+		assert_eq!(k.region(), Some(1));
+		assert!(KeycodeKey::try_from("E_10a").is_err());
+		assert!(KeycodeKey::try_from("E_1012").is_err());
+	}
+
 	#[test]
 	fn test_finger_comp() {
 		assert!(Thumb == Thumb);
@@ -117,7 +117,10 @@ impl<const R: usize, const C: usize> Layer<R, C, KeycodeKey> {
 				}
 				match random_keycode {
 					Some(v) => {
-						let replacement_key = KeycodeKey::default_from_keycode(v);
+						let mut replacement_key = KeycodeKey::default_from_keycode(v);
+						// blank keys in a region hand the region over to whatever fills them
+						replacement_key.set_region(key.region());
+						replacement_key.set_hold(key.hold());
 						self.set(i, j, replacement_key).unwrap(); // should always work
 					},
 					None => ()
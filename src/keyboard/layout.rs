@@ -8,19 +8,25 @@ use std::mem::discriminant;
 use crate::alc_error::AlcError;
 use crate::text_processor::keycode::Keycode::{self, *};
 use crate::text_processor::ngram::Ngram;
+use super::combo::Combo;
 use super::key::{KeyValue, KeycodeKey};
 use super::layer::Layer;
+use super::magic::MagicRule;
 use super::{LayoutPosition, LayoutPositionSequence};
 
 type KeycodePathMap = HashMap<Keycode, Vec<LayoutPositionSequence>>;
 type CorrespondingPositions = Vec<(LayoutPosition, LayoutPosition)>;
+type LayerAccessMap = HashMap<usize, Vec<(LayoutPositionSequence, LayoutPositionSequence)>>;
 
 /// A keyboard layout is a collection of layers of KeycodeKeys, plus additional info specifying how to navigate the layout, etc. (fill in later)
 /// Layouts with multiple layers must have a way to access every layer.
 /// For now, the only way to change layers is via a layer switch key. _LS(2) means that key switches to layer 2
+/// Combos and the rules of the magic key are kept alongside the layers since they don't occupy a position of their own
 #[derive(Debug, PartialEq, Clone)]
 pub struct Layout<const R: usize, const C: usize> {
 	layers: Vec<Layer<R, C, KeycodeKey>>,
+	combos: Vec<Combo>,
+	magic_rules: Vec<MagicRule>,
 	keycode_pathmap: KeycodePathMap,
 }
 impl<const R: usize, const C: usize> Layout<R, C> {
@@ -46,6 +52,52 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		}
 		// is there a possibility that the order could be non-deterministic? this could cause randomness despite fixed rng seed
 	}
+	/// Every position holding `k`, in layer, row, column order
+	pub fn positions_of(&self, k: Keycode) -> Vec<LayoutPosition> {
+		let mut positions: Vec<LayoutPosition> = vec![];
+		for layer_index in 0..self.layers.len() {
+			for row_index in 0..R {
+				for col_index in 0..C {
+					if self[(layer_index, row_index, col_index)].value() == k {
+						positions.push(LayoutPosition::new(layer_index, row_index, col_index));
+					}
+				}
+			}
+		}
+		positions
+	}
+	pub fn combos(&self) -> &[Combo] {
+		&self.combos
+	}
+	/// Replaces the layout's combos, checking that their positions exist in the layout
+	pub fn set_combos(&mut self, combos: Vec<Combo>) -> Result<(), AlcError> {
+		for combo in combos.iter() {
+			for lp in combo.positions() {
+				if lp.layer_index >= self.layers.len() || lp.row_index >= R || lp.col_index >= C {
+					return Err(AlcError::InvalidComboError(combo.to_string(), format!("{} is not in the layout", lp)));
+				}
+			}
+		}
+		self.combos = combos;
+		self.generate_pathmap()
+	}
+	pub fn magic_rules(&self) -> &[MagicRule] {
+		&self.magic_rules
+	}
+	/// Replaces the rules of the magic key, each previous keycode can only have one rule
+	pub fn set_magic_rules(&mut self, magic_rules: Vec<MagicRule>) -> Result<(), AlcError> {
+		for (i, rule) in magic_rules.iter().enumerate() {
+			if magic_rules[..i].iter().any(|other| other.previous() == rule.previous()) {
+				return Err(AlcError::InvalidMagicRuleError(rule.to_string(), format!("there is already a rule for {}", rule.previous())));
+			}
+		}
+		self.magic_rules = magic_rules;
+		Ok(())
+	}
+	/// What the magic key types after `previous`, if anything
+	pub fn magic_output(&self, previous: Keycode) -> Option<Keycode> {
+		self.magic_rules.iter().find(|rule| rule.previous() == previous && rule.value() != _NO).map(|rule| rule.value())
+	}
 	pub fn symmetric_position(&self, lp: LayoutPosition) -> LayoutPosition {
 		self.layers.first().unwrap().symmetric_position(lp) // would panic if layout is empty but that shouldn't normally be possible
 	}
@@ -61,6 +113,8 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		}
 		let mut layout = Layout { 
 			layers,
+			combos: vec![],
+			magic_rules: vec![],
 			keycode_pathmap: KeycodePathMap::default() 
 		};
 		layout.generate_pathmap().unwrap();
@@ -68,7 +122,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 	}
 
 
-	/// Randomly places [Keycode]s from `valid_keycodes` into the layout. Keys can be blocked off with __00 (_NO keycode, not moveable, not symmetric) to account for (currently) unsupported sizes and non-standard form factors. Prefilled keys are not randomized so that layouts can be "seeded" with "good" initial layouts.
+	/// Randomly places [Keycode]s from `valid_keycodes` into the layout. Keys can be blocked off with __00 (_NO keycode, not moveable, not symmetric) to account for (currently) unsupported sizes and non-standard form factors. Prefilled keys are not randomized so that layouts can be "seeded" with "good" initial layouts. Blank keys in a region (e.g., __101) keep their region once filled.
 	pub fn randomize(&mut self, rng: &mut impl Rng, valid_keycodes: &[Keycode]) -> Result<(), AlcError> {
 		let keycode_set: HashSet<Keycode> = self.keycode_pathmap.keys().cloned().collect();
 		// println!("keycodes already in layout {:?}", keycode_set);
@@ -85,6 +139,28 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 				break;
 			}
 		}
+		if !used_all_keycodes_flag {
+			// blank moveable combos get whatever the layers had no room for
+			for combo in self.combos.iter_mut() {
+				if !combo.key().is_moveable() || combo.value() != _NO {
+					continue;
+				}
+				while let Some(k) = valid_keycodes_to_draw_from.pop_front() {
+					if !keycode_set.contains(&k) {
+						combo.set_value(k);
+						break;
+					}
+				}
+			}
+		}
+		// the magic key doesn't take up a keycode, so blank moveable rules can type anything
+		for rule in self.magic_rules.iter_mut() {
+			if rule.key().is_moveable() && rule.value() == _NO {
+				if let Some(k) = valid_keycodes.iter().filter(|k| !k.is_layer_switch() && **k != _REP && **k != _MAGIC).choose(rng) {
+					rule.set_value(*k);
+				}
+			}
+		}
 		if !used_all_keycodes_flag {
 			println!("Warning: the keycodes {:?} may not have made it into the layout since they were left over. This could happen if the layout is too small or if you prefilled a lot of immovable spots.", valid_keycodes_to_draw_from)
 		}
@@ -93,16 +169,33 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 	}
 
 	/// Within a layout there can be multiple ways to type a keycode, so there can be multiple ways to type an ngram. Keep track of all of these
+	/// After the first keycode, the repeat key (_REP) is another way to type the previous keycode again and the magic key (_MAGIC) is another way to type what its rules say comes after the previous keycode
 	pub fn ngram_to_sequences(&self, ngram: &Ngram) -> Result<Vec<LayoutPositionSequence>, AlcError> {
 		let mut output_sequences_to_ngram: Vec<LayoutPositionSequence> = vec![];
 
 		let ngram_iter = ngram.clone().into_iter();
+		let mut previous_keycode: Option<Keycode> = None;
 		for keycode in ngram_iter {
 			// if keycode == _AMPR {
 			// 	pnic!("found &");
 			// }
 			// println!("output sequences {:?} at start,  keycode {}", output_sequences_to_ngram, keycode);
-			let sequences_to_keycode = self.paths_to_keycode(keycode)?;
+			let mut sequences_to_keycode: Vec<&LayoutPositionSequence> = match self.keycode_pathmap.get(&keycode) {
+				Some(v) => v.iter().collect(),
+				None => vec![],
+			};
+			if let Some(previous) = previous_keycode {
+				if previous == keycode {
+					sequences_to_keycode.extend(self.keycode_pathmap.get(&_REP).into_iter().flatten());
+				}
+				if self.magic_output(previous) == Some(keycode) {
+					sequences_to_keycode.extend(self.keycode_pathmap.get(&_MAGIC).into_iter().flatten());
+				}
+			}
+			if sequences_to_keycode.is_empty() {
+				self.paths_to_keycode(keycode)?;
+			}
+			previous_keycode = Some(keycode);
 			// let sequences_to_keycode = match self.paths_to_keycode(keycode) {
 			// 	Some(p) => p,
 			// 	None => {
@@ -111,7 +204,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 			// 	},
 			// };
 			if output_sequences_to_ngram.is_empty() {
-				output_sequences_to_ngram = sequences_to_keycode.to_vec();
+				output_sequences_to_ngram = sequences_to_keycode.into_iter().cloned().collect();
 				// output_sequences_to_ngram = sequences_to_keycode.clone();
 			} else {
 				let mut temp_sequences_to_ngram: Vec<LayoutPositionSequence> = vec![];
@@ -141,9 +234,6 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 			}
 		}
 
-		#[allow(unused_assignments)]
-		let mut swap_happened = false;
-
 		// Bunch of checks for issues that should be easier to resolve in whatever calls swap rather than within swap.
 		if p1 == p2 {
 			return Ok(false);
@@ -153,7 +243,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		if !k1.is_moveable() || !k2.is_moveable() {
 			return Err(AlcError::GenericError(String::from("Error for the developer! Don't try to swap unmoveable positions.")));
 		}
-		if let _LS(_i) = k2.value() {
+		if k2.value().is_layer_switch() {
 			return Err(AlcError::GenericError(String::from("Error for the developer! Place layer switches in the first position of the swap and disallow swaps where both keys are layer switches.")));
 		}
 		if let _LST(_i, _j) = k1.value() {
@@ -165,86 +255,85 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		if !k1.is_symmetric() && k2.is_symmetric() {
 			return Err(AlcError::GenericError(String::from("Error for the developer! Place symmetric keys in the first position of the swap.")));
 		}
-		if let _LS(_i) = k1.value() {
+		if k1.region() != k2.region() {
+			return Err(AlcError::GenericError(String::from("Error for the developer! Keys can only be swapped within their own region.")));
+		}
+		if k1.value().is_layer_switch() {
 			if p1.layer_index != p2.layer_index {
 				return Err(AlcError::GenericError(String::from("Error for the developer! Swaps involving layer switches must occur within the same layer otherwise layers could become unreachable.")));
 			}
 			if k2.is_symmetric() {
 				return Err(AlcError::GenericError(String::from("Error for the developer! Can't swap a layer switch key with a symmetric key.")));
 			}
-			if k1.is_symmetric() {
-				return Err(AlcError::GenericError(String::from("Error for the developer! Can't have a layer switch that is also symmetric due to additionaly complexity. This should be caught when reading in a Key from a string.")));
-			}
-		}
-		// cursed things
-		let self_clone = self.clone();
-		let k1 = self.get_mut_from_layout_position(p1).unwrap();
-		let k1_clone = &self_clone[p1];
-		let k2_clone = &self_clone[p2];
-		if let _LS(target_layer) = k1.value() {
-			// Layer switches need to be in the same layer position in the starting layer and the target layer. So, if the first position is a layer switch, its counterpart must be in:
-			let p1_counterpart = LayoutPosition::new(target_layer, p1.row_index, p1.col_index);
-			let p2_counterpart = LayoutPosition::new(target_layer, p2.row_index, p2.col_index);
-			let k2_counterpart_clone = &self_clone[p2_counterpart];
-			// I think these are harder to handle in the calling function, so just have nothing happen here
-			if !k2_counterpart_clone.is_moveable() {
-				// println!("Warning: attempted to swap a layer switch with position x: {} and found that x's corresponding position {} was not moveable. Doing nothing instead.", p2, p2_counterpart);
+		}
+		// A swap moves up to four keys: the two positions themselves plus, if the first key is symmetric, their mirrored positions. Any layer switch that moves also takes its LST counterpart in the target layer along with it. Check everything first and only then move keys, since issues here are harder to handle in the calling function and nothing should happen in that case.
+		let mut pairs = vec![(p1, p2)];
+		if k1.is_symmetric() {
+			if p2.col_index as f64 == (C as f64 - 1.0) / 2.0 {
+				// println!("Warning: symmetric p1 {} is being swapped into the center column {}, meaning p1's mirror has no where to go, doing nothing instead.", p1, p2);
 				return Ok(false);
 			}
-			if k2_counterpart_clone.is_symmetric() {
-				// println!("Warning: attempted to swap a layer switch with position x: {} and found that x's corresponding position {} was symmetric, making the swap not valid. Doing nothing instead.", p2, p2_counterpart);
-				return Ok(false);
+			let p1_mirror = self.symmetric_position(p1);
+			let p2_mirror = self.symmetric_position(p2);
+			// swapping a symmetric key with its own mirror is just a regular swap
+			if p2_mirror == p1 {
+				self.swap_keys(p1, p2);
+				self.generate_pathmap()?;
+				return Ok(true);
 			}
-			// yeah gonna want to redo this section once I understand more
-			k1.replace_with(k2_clone);
-			let k2 = self.get_mut_from_layout_position(p2).unwrap();
-			k2.replace_with(k1_clone);
-
-			let k1_counterpart = self.get_mut_from_layout_position(p1_counterpart).unwrap();
-			k1_counterpart.replace_with(k2_counterpart_clone);
-			let k2_counterpart = self.get_mut_from_layout_position(p2_counterpart).unwrap();
-			let k1_counterpart_clone = &self_clone[p1_counterpart];
-			k2_counterpart.replace_with(k1_counterpart_clone);
-			swap_happened = true;
-		} else if k1_clone.is_symmetric() {
-			let p1_counterpart = self_clone.symmetric_position(p1);
-			if p2.col_index as f64 == (C as f64 - 1.0) / 2.0 {
-				// println!("Warning: symmetric p1 {} is being swapped into the center column {}, meaning p1's counterpart {} has no where to go, doing nothing instead.", p1, p2, p1_counterpart);
+			let k2_mirror = &self[p2_mirror];
+			if !k2_mirror.is_moveable() || k2_mirror.region() != self[p1_mirror].region() {
 				return Ok(false);
 			}
-			let p2_counterpart = self_clone.symmetric_position(p2);
-			let k2_counterpart_clone = &self_clone[p2_counterpart];
-			if !k2_counterpart_clone.is_moveable() {
-				// println!("Warning: attempted to swap a symmetric key with position x: {} and found that x's corresponding position {} was not moveable. Doing nothing instead.", p2, p2_counterpart);
+			if k2_mirror.value().is_layer_switch() || discriminant(&k2_mirror.value()) == discriminant(&_LST(0, 0)) {
 				return Ok(false);
 			}
-			// if let _LS(_target_layer) = k2_counterpart_clone.value() {
-			// 	// println!("Warning: attempted symmetric swap but p2 {}'s counterpart {} is a layer switch. Doing nothing instead.", p2, p2_counterpart);
-			// 	return false;
-			// }
-			if discriminant(&k2_counterpart_clone.value()) == discriminant(&_LS(0)) || discriminant(&k2_counterpart_clone.value()) == discriminant(&_LST(0, 0)) {
+			if k1.value().is_layer_switch() && k2_mirror.is_symmetric() {
 				return Ok(false);
 			}
-			k1.replace_with(k2_clone);
-			let k2 = self.get_mut_from_layout_position(p2).unwrap();
-			k2.replace_with(k1_clone);
-
-			let k1_counterpart = self.get_mut_from_layout_position(p1_counterpart).unwrap();
-			k1_counterpart.replace_with(k2_counterpart_clone);
-			let k2_counterpart = self.get_mut_from_layout_position(p2_counterpart).unwrap();
-			let k1_counterpart_clone = &self_clone[p1_counterpart];
-			k2_counterpart.replace_with(k1_counterpart_clone);
-			swap_happened = true;
-		} else {
-			k1.replace_with(k2_clone);
-			let k2 = self.get_mut_from_layout_position(p2).unwrap();
-			k2.replace_with(k1_clone);
-			swap_happened = true;
+			pairs.push((p1_mirror, p2_mirror));
+		}
+		let mut layer_switch_pairs: Vec<(LayoutPosition, LayoutPosition)> = vec![];
+		for (a, b) in pairs.iter() {
+			if let Some(target_layer) = self[*a].value().layer_target() {
+				// the mirror of a symmetric key can be a layer switch even if the key itself isn't
+				if a.layer_index != b.layer_index {
+					return Ok(false);
+				}
+				// Layer switches need to be in the same layer position in the starting layer and the target layer. So, if the first position is a layer switch, its counterpart must be in:
+				let a_counterpart = LayoutPosition::new(target_layer, a.row_index, a.col_index);
+				let b_counterpart = LayoutPosition::new(target_layer, b.row_index, b.col_index);
+				let kb_counterpart = &self[b_counterpart];
+				if !kb_counterpart.is_moveable() || kb_counterpart.is_symmetric() || kb_counterpart.region() != self[a_counterpart].region() {
+					// println!("Warning: attempted to swap a layer switch with position x: {} and found that x's corresponding position {} was not moveable or symmetric, making the swap not valid. Doing nothing instead.", b, b_counterpart);
+					return Ok(false);
+				}
+				if kb_counterpart.value().is_layer_switch() || discriminant(&kb_counterpart.value()) == discriminant(&_LST(0, 0)) {
+					return Ok(false);
+				}
+				layer_switch_pairs.push((a_counterpart, b_counterpart));
+			}
+		}
+		for (a, b) in pairs.into_iter().chain(layer_switch_pairs) {
+			self.swap_keys(a, b);
 		}
 		self.generate_pathmap()?;
 		// self.keycode_pathmap = keycode_path_map_from_layout(self.layers.clone()).unwrap();
 		
-		Ok(swap_happened)
+		Ok(true)
+	}
+
+	/// Exchanges the keys (values and flags) at two positions without any checks
+	fn swap_keys(&mut self, p1: LayoutPosition, p2: LayoutPosition) {
+		let k1 = self[p1];
+		let k2 = self[p2];
+		// mod-tap holds stay where they are, only the tapped keycodes move
+		let key1 = self.get_mut_from_layout_position(p1).unwrap();
+		key1.replace_with(&k2);
+		key1.set_hold(k1.hold());
+		let key2 = self.get_mut_from_layout_position(p2).unwrap();
+		key2.replace_with(&k1);
+		key2.set_hold(k2.hold());
 	}
 
 	pub fn replace(&mut self, p: LayoutPosition, value: Keycode) -> Result<bool, AlcError> {
@@ -267,7 +356,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		if let _LST(_i, _j) = k.value() {
 			return Err(AlcError::GenericError(String::from("Error for the developer! Not allowed to replace LST")));
 		}
-		if let _LS(_target_layer) = k.value() {
+		if k.value().is_layer_switch() {
 			return Err(AlcError::GenericError(format!("Error for the developer! Not allowed to replace the layer switch ({}).", p)));
 		}
 		if !k.is_moveable() {
@@ -302,32 +391,117 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		Ok(p)
 	}
 
+	/// First position of a swap: anything moveable except blank keys and the LST counterparts of layer switches
+	fn is_valid_first_swap_position(&self, p1: LayoutPosition) -> bool {
+		let k1 = &self[p1];
+		k1.is_moveable() && discriminant(&k1.value()) != discriminant(&_LST(1, 2)) && k1.value() != _NO
+	}
+
+	/// Second position of a swap given the first. Layer switches can only swap with non-symmetric keys in the same layer, symmetric keys must always be in the first position, and keys only swap within their own region
+	fn is_valid_second_swap_position(&self, p1: LayoutPosition, p2: LayoutPosition) -> bool {
+		let k1 = &self[p1];
+		let k2 = &self[p2];
+		if !k2.is_moveable() || k1.region() != k2.region() || k2.value().is_layer_switch() || discriminant(&k2.value()) == discriminant(&_LST(1, 2)) {
+			return false;
+		}
+		if k1.value().is_layer_switch() {
+			!k2.is_symmetric() && p1.layer_index == p2.layer_index
+		} else {
+			k1.is_symmetric() || !k2.is_symmetric()
+		}
+	}
+
+	/// Keys that can trade keycodes with a moveable combo: moveable, not symmetric, not in a region and not a layer switch or its LST counterpart
+	fn is_valid_combo_swap_position(&self, combo_index: usize, p: LayoutPosition) -> bool {
+		let k = &self[p];
+		k.is_moveable() && !k.is_symmetric() && k.region().is_none() && !k.value().is_layer_switch() && discriminant(&k.value()) != discriminant(&_LST(1, 2)) && k.value() != self.combos[combo_index].value()
+	}
+
+	/// Picks a moveable combo and a key to trade keycodes with, or None if there are no moveable combos. Combos are picked in proportion to how many of them there are compared to moveable keys, so layouts without moveable combos don't use up any randomness
+	pub fn generate_random_valid_combo_swap(&self, rng: &mut impl Rng) -> Option<(usize, LayoutPosition)> {
+		let moveable_combos: Vec<usize> = (0..self.combos.len()).filter(|i| self.combos[*i].key().is_moveable()).collect();
+		if moveable_combos.is_empty() {
+			return None;
+		}
+		let moveable_key_count = self.layers.iter().map(|layer| {
+			(0..R).map(|r| (0..C).filter(|c| layer[(r, *c)].is_moveable()).count()).sum::<usize>()
+		}).sum::<usize>();
+		let pick = rng.gen_range(0..moveable_combos.len() + moveable_key_count);
+		if pick >= moveable_combos.len() {
+			return None;
+		}
+		let combo_index = moveable_combos[pick];
+		let fallback_count = 100;
+		for _i in 0..fallback_count {
+			let p = self.generate_random_position(rng);
+			if self.is_valid_combo_swap_position(combo_index, p) {
+				return Some((combo_index, p));
+			}
+		}
+		None
+	}
+
+	/// Trades the keycode of a moveable combo with the keycode at `p`, which lets the optimizer choose which keycodes go on combos
+	pub fn swap_combo(&mut self, combo_index: usize, p: LayoutPosition) -> Result<bool, AlcError> {
+		if combo_index >= self.combos.len() || !self.combos[combo_index].key().is_moveable() {
+			return Err(AlcError::GenericError(String::from("Error for the developer! Only swap with moveable combos.")));
+		}
+		if !self.is_valid_combo_swap_position(combo_index, p) {
+			return Ok(false);
+		}
+		let combo_value = self.combos[combo_index].value();
+		let key_value = self[p].value();
+		self.combos[combo_index].set_value(key_value);
+		self.get_mut_from_layout_position(p).unwrap().set_value(combo_value);
+		self.generate_pathmap()?;
+		Ok(true)
+	}
+
+	/// Picks a moveable magic rule to change, or None if there are no moveable rules. Like [Layout::generate_random_valid_combo_swap], rules are picked in proportion to how many of them there are compared to moveable keys
+	pub fn generate_random_valid_magic_replace(&self, rng: &mut impl Rng) -> Option<usize> {
+		let moveable_rules: Vec<usize> = (0..self.magic_rules.len()).filter(|i| self.magic_rules[*i].key().is_moveable()).collect();
+		if moveable_rules.is_empty() {
+			return None;
+		}
+		let moveable_key_count = self.layers.iter().map(|layer| {
+			(0..R).map(|r| (0..C).filter(|c| layer[(r, *c)].is_moveable()).count()).sum::<usize>()
+		}).sum::<usize>();
+		let pick = rng.gen_range(0..moveable_rules.len() + moveable_key_count);
+		moveable_rules.get(pick).copied()
+	}
+
+	/// Changes what the magic key types after the previous keycode of a moveable rule. Returns false if nothing changed
+	pub fn replace_magic(&mut self, rule_index: usize, value: Keycode) -> Result<bool, AlcError> {
+		if rule_index >= self.magic_rules.len() || !self.magic_rules[rule_index].key().is_moveable() {
+			return Err(AlcError::GenericError(String::from("Error for the developer! Only replace moveable magic rules.")));
+		}
+		if value.is_layer_switch() || matches!(value, _LST(_, _) | _REP | _MAGIC | _NO) || self.magic_rules[rule_index].value() == value {
+			return Ok(false);
+		}
+		self.magic_rules[rule_index].set_value(value);
+		Ok(true)
+	}
+
 	pub fn generate_random_valid_swap(&self, rng: &mut impl Rng) -> Result<(LayoutPosition, LayoutPosition), AlcError> {
 		let mut p1 = self.generate_random_moveable_position(rng)?;
-		let mut k1 = &self[p1];
 		let mut p2 = self.generate_random_moveable_position(rng)?;
-		let mut k2 = &self[p2];
 		let mut count = 0;
 		let fallback_count = 100;
 
-		while discriminant(&k1.value()) == discriminant(&_LST(1, 2)) || k1.value() == _NO {
+		while !self.is_valid_first_swap_position(p1) {
 			p1 = self.generate_random_moveable_position(rng)?;
-			k1 = &self[p1];
 
 			count += 1;
 			if count >= fallback_count {
 				return Err(AlcError::GenericError(String::from("Error for developer! Only finding _NO keycodes.")));
 			}
 		}
+		let k1 = &self[p1];
 		count = 0;
 
-		if let _LS(_i) = k1.value() {
-			if k1.is_symmetric() {
-				return Err(AlcError::GenericError(String::from("Error for the developer! Can't have a layer switch that is also symmetric due to additionaly complexity. This should be caught when reading in a Key from a string.")));
-			}
-			while k2.is_symmetric() || (p1.layer_index != p2.layer_index) || std::mem::discriminant(&k2.value()) == std::mem::discriminant(&_LS(1)) || std::mem::discriminant(&k2.value()) == std::mem::discriminant(&_LST(1, 2)) {
+		if k1.value().is_layer_switch() {
+			while !self.is_valid_second_swap_position(p1, p2) {
 				p2 = self.generate_random_moveable_position(rng)?;
-				k2 = &self[p2];
 				count += 1;
 				if count >= fallback_count {
 					return Err(AlcError::SwapFallbackError(fallback_count, String::from("key 1 was a layer switch and either i) no non-symmetric key 2s could be found or ii) no key 2s could be found in the same layer or iii) not non-layer switch key 2s could be found")));
@@ -335,9 +509,8 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 			}
 			Ok((p1, p2))
 		} else {
-			while std::mem::discriminant(&k2.value()) == std::mem::discriminant(&_LS(1)) || (!k1.is_symmetric() && k2.is_symmetric()) || std::mem::discriminant(&k2.value()) == std::mem::discriminant(&_LST(1, 2)) {
+			while !self.is_valid_second_swap_position(p1, p2) {
 				p2 = self.generate_random_moveable_position(rng)?;
-				k2 = &self[p2];
 				count += 1;
 				if count >= fallback_count {
 					return Err(AlcError::SwapFallbackError(fallback_count, String::from("key 1 was not a layer switch but proper k2 could not be found")));
@@ -347,6 +520,29 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		}
 	}
 
+	/// Every swap that [Self::generate_random_valid_swap] could return, with each unordered pair of positions listed once.
+	pub fn valid_swaps(&self) -> Vec<(LayoutPosition, LayoutPosition)> {
+		let mut positions: Vec<LayoutPosition> = vec![];
+		for layer_index in 0..self.layers.len() {
+			for row_index in 0..R {
+				for col_index in 0..C {
+					positions.push(LayoutPosition::new(layer_index, row_index, col_index));
+				}
+			}
+		}
+		let mut swaps: Vec<(LayoutPosition, LayoutPosition)> = vec![];
+		for (i, p1) in positions.iter().enumerate() {
+			for p2 in positions.iter().skip(i + 1) {
+				if self.is_valid_first_swap_position(*p1) && self.is_valid_second_swap_position(*p1, *p2) {
+					swaps.push((*p1, *p2));
+				} else if self.is_valid_first_swap_position(*p2) && self.is_valid_second_swap_position(*p2, *p1) {
+					swaps.push((*p2, *p1));
+				}
+			}
+		}
+		swaps
+	}
+
 	pub fn generate_valid_replace_position(&self, rng: &mut impl Rng) -> Option<LayoutPosition> {
 		let mut p = self.generate_random_moveable_position(rng).unwrap();
 		let mut k = &self[p];
@@ -361,7 +557,8 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		};
 		let fallback_count = 100;
 		let mut count = 0;
-		while paths.len() <= 1 || std::mem::discriminant(&k.value()) == std::mem::discriminant(&_LS(1)) || std::mem::discriminant(&k.value()) == std::mem::discriminant(&_LST(1, 2)) || !k.is_moveable() || k.is_symmetric() {
+		// keys in a region are only rearranged by swaps so that keycodes pinned to a region can't be replaced away from it
+		while paths.len() <= 1 || k.value().is_layer_switch() || std::mem::discriminant(&k.value()) == std::mem::discriminant(&_LST(1, 2)) || !k.is_moveable() || k.is_symmetric() || k.region().is_some() {
 			p = self.generate_random_moveable_position(rng).unwrap();
 			k = &self[p];
 			paths = match self.keycode_pathmap.get(&k.value()) {
@@ -387,11 +584,20 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 		let mut visited_positions: Vec<LayoutPosition> = vec![];
 		for (pathmap_keycode, paths) in pathmap {
 			for path in paths {
-				let last_position = path.last().unwrap();
-				visited_positions.push(*last_position);
-				let found_keycode = &self[*last_position].value();
-				if *pathmap_keycode != *found_keycode {
-					return Err(AlcError::IncorrectPathmapError(*pathmap_keycode, *last_position, *found_keycode));
+				// paths through a toggled layer end on the toggle's LST counterpart, so the key itself is the last position that isn't an LST
+				let (key_index, key_position) = path.iter().enumerate().rev().find(|(_i, lp)| discriminant(&self[**lp].value()) != discriminant(&_LST(0, 0))).unwrap();
+				if let Some((start, end)) = path.combos().iter().find(|(start, end)| key_index >= *start && key_index < *end) {
+					let combo_positions: Vec<LayoutPosition> = path.iter().skip(*start).take(end - start).cloned().collect();
+					match self.combos.iter().find(|combo| combo.positions() == combo_positions.as_slice()) {
+						Some(combo) if combo.value() == *pathmap_keycode => continue,
+						Some(combo) => return Err(AlcError::IncorrectPathmapError(*pathmap_keycode, *key_position, combo.value())),
+						None => return Err(AlcError::GenericError(format!("pathmap has a combo on {} that isn't in the layout", path))),
+					}
+				}
+				visited_positions.push(*key_position);
+				let found_key = &self[*key_position];
+				if *pathmap_keycode != found_key.value() && Some(*pathmap_keycode) != found_key.tap() && Some(*pathmap_keycode) != found_key.hold() {
+					return Err(AlcError::IncorrectPathmapError(*pathmap_keycode, *key_position, found_key.value()));
 				}
 			}
 		}
@@ -427,7 +633,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 				for col_index in 0..C {
 					let lp = LayoutPosition::new(layer_index, row_index, col_index);
 					let key = &self[lp];
-					if let _LS(target_layer) = key.value() {
+					if let Some(target_layer) = key.value().layer_target() {
 						// println!("position {}", lp);
 						let lp_corresponding = LayoutPosition::new(target_layer, row_index, col_index);
 						let key_corresponding = &self[lp_corresponding];
@@ -442,7 +648,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 					} else if let _LST(target_layer, source_layer) = key.value() {
 						let lp_corresponding = LayoutPosition::new(source_layer, row_index, col_index);
 						let key_corresponding = &self[lp_corresponding];
-						if let _LS(new_target_layer) = key_corresponding.value() {
+						if let Some(new_target_layer) = key_corresponding.value().layer_target() {
 							if target_layer != new_target_layer {
 								incorrect_layer_switch_locations.push((lp, lp_corresponding));		
 							}
@@ -465,47 +671,76 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 
 
 
+	/// Layer switches (_LS) and the hold of layer-tap keys (_LTAP) are pressed before the key. Toggles (_TG) are pressed before the key and their LST counterpart is pressed after it to toggle back, see [crate::objective::scoring::Score::cancel_layer_switches] for how runs of keys on the same layer are counted. One-shot layers (_OSL) are pressed before the key and only apply to that key. Tapping a layer-tap key types its tap keycode. Combos are an alternative path to their keycode, reached the same way as the keys on their layer.
 	pub fn generate_pathmap(&mut self) -> Result<(), AlcError> {
 		let mut pathmap = KeycodePathMap::default();
 		let mut layer_switch_pathmap =  KeycodePathMap::default();
+		// for each layer, what to press before and after a key on that layer
+		let mut layer_access = LayerAccessMap::default();
+		layer_access.insert(0, vec![(LayoutPositionSequence::from_vector(vec![]), LayoutPositionSequence::from_vector(vec![]))]);
 		for (layer_num, layer) in self.layers.iter().enumerate() {
 			for r in 0..R {
 				for c in 0..C {
 					let key = &layer[(r, c)];
 					let key_value = key.value();
-					if key_value == _NO {
+					if (key_value == _NO && key.hold().is_none()) || discriminant(&key_value) == discriminant(&_LST(0, 0)) {
 						continue;
 					}
 					let layout_position = LayoutPosition::new(layer_num, r, c);
-					let layout_position_sequence = LayoutPositionSequence::from_vector(vec![layout_position]);
-					if layer_num == 0 {
-						match key_value {
-							_LS(_i) => layer_switch_pathmap.entry(key_value).or_insert(vec![]).push(layout_position_sequence),
-							_ => pathmap.entry(key_value).or_insert(vec![]).push(layout_position_sequence),
+					// check that layer_num is reachable. If layer is currently not reachable, could pass until after the rest of the layout is processed in case there is a downward layer move, but not going to implement that now since QMK does not recommend having layer switches like that
+					let sequences_to_reach_layer = match layer_access.get(&layer_num) {
+						Some(v) => v.clone(),
+						None => return Err(AlcError::LayerAccessError(layer_num)),
+					};
+					for (before, after) in sequences_to_reach_layer {
+						let mut sequence = before.clone();
+						sequence.push(layout_position);
+						sequence.append(&mut after.clone());
+						match key_value.layer_target() {
+							Some(target_layer) => {
+								layer_switch_pathmap.entry(key_value).or_insert(vec![]).push(sequence.clone());
+								let mut target_before = before.clone();
+								target_before.push(layout_position);
+								let mut target_after = after.clone();
+								if let _TG(_i) = key_value {
+									let mut toggle_off = LayoutPositionSequence::from_vector(vec![LayoutPosition::new(target_layer, r, c)]);
+									toggle_off.append(&mut target_after);
+									target_after = toggle_off;
+								}
+								layer_access.entry(target_layer).or_insert(vec![]).push((target_before, target_after));
+							},
+							None => if key_value != _NO {
+								pathmap.entry(key_value).or_insert(vec![]).push(sequence.clone())
+							},
 						}
-					} else if discriminant(&key_value) == discriminant(&_LST(0, 0)) {
-						continue;
-					} else {
-						// check that layer_num is reachable. If layer is currently not reachable, could pass until after the rest of the layout is processed in case there is a downward layer move, but not going to implement that now since QMK does not recommend having layer switches like that
-						let layer_switches = layer_switch_pathmap.clone();
-						let sequences_to_reach_layer = match layer_switches.get(&_LS(layer_num)) {
-							Some(v) => v.clone(),
-							None => return Err(AlcError::LayerAccessError(layer_num)),
-						};
-						// let sequences_to_reach_layer = &layer_switch_pathmap.clone()[&_LS(layer_num)];
-						for sequence in sequences_to_reach_layer {
-							let mut sequence_clone = sequence.clone();
-							sequence_clone.push(layout_position);
-							match key_value {
-								_LS(_i) => layer_switch_pathmap.entry(key_value).or_insert(vec![]).push(sequence_clone),
-								_ => pathmap.entry(key_value).or_insert(vec![]).push(sequence_clone),
-							}
-							
+						if let Some(tap) = key.tap() {
+							pathmap.entry(tap).or_insert(vec![]).push(sequence.clone());
+						}
+						if let Some(hold) = key.hold() {
+							let mut hold_sequence = before.clone();
+							hold_sequence.push_hold(layout_position);
+							hold_sequence.append(&mut after.clone());
+							pathmap.entry(hold).or_insert(vec![]).push(hold_sequence);
 						}
 					}
 				}
 			}
 		}
+		for combo in self.combos.iter() {
+			if combo.value() == _NO {
+				continue;
+			}
+			let sequences_to_reach_layer = match layer_access.get(&combo.layer_index()) {
+				Some(v) => v.clone(),
+				None => return Err(AlcError::LayerAccessError(combo.layer_index())),
+			};
+			for (before, mut after) in sequences_to_reach_layer {
+				let mut sequence = before.clone();
+				sequence.push_combo(combo.positions());
+				sequence.append(&mut after);
+				pathmap.entry(combo.value()).or_insert(vec![]).push(sequence);
+			}
+		}
 		for (key, value) in layer_switch_pathmap {
 			pathmap.insert(key, value);
 		}
@@ -529,7 +764,7 @@ impl<const R: usize, const C: usize> Layout<R, C> {
 					// if visited.contains(&current_pos) {
 					// 	continue;
 					// } else 
-					if visited.contains(&current_pos) || !k.is_moveable() || k.is_symmetric() || discriminant(&k.value()) == discriminant(&Keycode::_LS(0)) || discriminant(&k.value()) == discriminant(&Keycode::_LST(0, 0,)) {
+					if visited.contains(&current_pos) || !k.is_moveable() || k.is_symmetric() || k.value().is_layer_switch() || discriminant(&k.value()) == discriminant(&Keycode::_LST(0, 0,)) {
 						continue;
 					} else {
 						self.get_mut(layer_index, row_index, col_index).unwrap().set_value(Keycode::_NO);
@@ -558,7 +793,7 @@ impl<const R: usize, const C: usize> TryFrom<&str> for Layout<R, C> {
 			for row_index in 0..R {
 				for col_index in 0..C {
 					let k = &layers[layer_index][(row_index, col_index)];
-					if let _LS(target_layer) = k.value() {
+					if let Some(target_layer) = k.value().layer_target() {
 						let k_counterpart = layers.get_mut(target_layer).unwrap().get_mut(row_index, col_index).unwrap();
 						k_counterpart.set_value(_LST(target_layer, layer_index));
 					}
@@ -566,7 +801,7 @@ impl<const R: usize, const C: usize> TryFrom<&str> for Layout<R, C> {
 			}
 		}
 
-		let mut layout = Layout { layers, keycode_pathmap: KeycodePathMap::default() };
+		let mut layout = Layout { layers, combos: vec![], magic_rules: vec![], keycode_pathmap: KeycodePathMap::default() };
 		layout.generate_pathmap()?;
 
 		let (v1, v2) = layout.verify_layout_correctness()?;
@@ -576,6 +811,25 @@ impl<const R: usize, const C: usize> TryFrom<&str> for Layout<R, C> {
 		if !v2.is_empty() {
 			return Err(AlcError::LayoutSymmetryError(v2));
 		}
+		let mut region_sizes: HashMap<u8, usize> = HashMap::new();
+		for layer_index in 0..layout.len() {
+			for row_index in 0..R {
+				for col_index in 0..C {
+					let k = &layout[(layer_index, row_index, col_index)];
+					if let Some(region) = k.region() {
+						let count = region_sizes.entry(region).or_insert(0);
+						if k.is_moveable() {
+							*count += 1;
+						}
+					}
+				}
+			}
+		}
+		let mut regions: Vec<(u8, usize)> = region_sizes.into_iter().collect();
+		regions.sort();
+		if let Some((region, _size)) = regions.into_iter().find(|(_region, size)| *size < 2) {
+			return Err(AlcError::LayoutRegionError(region));
+		}
 		
 		Ok(layout)
 	}
@@ -603,13 +857,28 @@ impl<const R: usize, const C: usize> fmt::Display for Layout<R, C> {
 			writeln!(f, "___Layer {}___", i)?;
 			writeln!(f, "{}", layer)?;
 		}
+		if !self.combos.is_empty() {
+			writeln!(f, "___Combos___")?;
+			for combo in self.combos.iter() {
+				writeln!(f, "{}", combo)?;
+			}
+		}
+		if !self.magic_rules.is_empty() {
+			writeln!(f, "___Magic___")?;
+			for rule in self.magic_rules.iter() {
+				writeln!(f, "{}", rule)?;
+			}
+		}
 		if f.alternate() {
 			let mut keys: Vec<&Keycode> = self.keycode_pathmap.keys().collect();
 			keys.sort();
 			for k in keys {
 				let key_text = match k {
 					_LS(i) => format!("_LS{}", i),
-					_ => k.to_string(),
+					_TG(i) => format!("_TG{}", i),
+					_OSL(i) => format!("_OSL{}", i),
+					_LTAP(i) => format!("_LTAP{}", i),
+					_ => format!("_{}", k.display_name()),
 				};
 				write!(f, "{}: ", key_text)?;
 				for seq in self.keycode_pathmap[k].iter() {
@@ -633,7 +902,10 @@ impl<const R: usize, const C: usize> fmt::Binary for Layout<R, C> {
 			for k in keys {
 				let key_text = match k {
 					_LS(i) => format!("_LS{}", i),
-					_ => k.to_string(),
+					_TG(i) => format!("_TG{}", i),
+					_OSL(i) => format!("_OSL{}", i),
+					_LTAP(i) => format!("_LTAP{}", i),
+					_ => format!("_{}", k.display_name()),
 				};
 				write!(f, "{}: ", key_text)?;
 				for seq in self.keycode_pathmap[k].iter() {
@@ -827,6 +1099,239 @@ use super::*;
 		Ok(())
 	}
 
+	#[test]
+	fn test_symmetric_layer_switch_swap() -> Result<(), AlcError> {
+		let mut layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			A_10 B_10 C_10 H_10
+			LS1_11 E_10 F_10 LS1_11
+			___Layer 1___
+			D_10 I_10 J_10 K_10
+			__10 L_10 M_10 __10
+		").unwrap();
+		assert!(layout.swap(LayoutPosition::new(0, 1, 0), LayoutPosition::new(0, 0, 1))?);
+		assert_eq!(layout[(0, 0, 1)].value(), _LS(1));
+		assert_eq!(layout[(0, 0, 2)].value(), _LS(1));
+		assert_eq!(layout[(0, 1, 0)].value(), _B);
+		assert_eq!(layout[(0, 1, 3)].value(), _C);
+		assert_eq!(layout[(1, 0, 1)].value(), _LST(1, 0));
+		assert_eq!(layout[(1, 0, 2)].value(), _LST(1, 0));
+		assert_eq!(layout[(1, 1, 0)].value(), _I);
+		assert_eq!(layout[(1, 1, 3)].value(), _J);
+		assert_eq!(layout.verify_layout_correctness()?, (vec![], vec![]));
+		// layer switches stay in their own layer
+		assert!(!layout.valid_swaps().iter().any(|(p1, p2)| discriminant(&layout[*p1].value()) == discriminant(&_LS(0)) && p2.layer_index != 0));
+
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		for _i in 0..100 {
+			let (p1, p2) = layout.generate_random_valid_swap(&mut rng)?;
+			layout.swap(p1, p2)?;
+			assert_eq!(layout.verify_layout_correctness()?, (vec![], vec![]));
+			layout.verify_pathmap_correctness()?;
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_layer_key_kinds() -> Result<(), AlcError> {
+		let mut layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			A_10 B_10 C_10 H_10
+			TG1_10 E_10 F_10 LT2:SPC_10
+			___Layer 1___
+			D_10 I_10 J_10 K_10
+			__10 L_10 OSL2_10 M_10
+			___Layer 2___
+			N_10 O_10 P_10 Q_10
+			R_10 S_10 __10 __10
+		").unwrap();
+		layout.verify_pathmap_correctness()?;
+		assert_eq!(layout.paths_to_keycode(_SPC)?, &vec![LayoutPositionSequence::from_tuples(vec![(0, 1, 3)])]);
+		// layer 2 is reachable by holding the layer-tap or through the one-shot layer on the toggled layer 1
+		assert_eq!(layout.paths_to_keycode(_N)?, &vec![
+			LayoutPositionSequence::from_tuples(vec![(0, 1, 3), (2, 0, 0)]),
+			LayoutPositionSequence::from_tuples(vec![(0, 1, 0), (1, 1, 2), (2, 0, 0), (1, 1, 0)]),
+		]);
+		assert!(layout.swap(LayoutPosition::new(0, 1, 3), LayoutPosition::new(0, 0, 0))?);
+		assert_eq!(layout[(0, 0, 0)].tap(), Some(_SPC));
+		assert_eq!(layout[(2, 0, 0)].value(), _LST(2, 0));
+		assert_eq!(layout.paths_to_keycode(_SPC)?, &vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 0)])]);
+
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		for _i in 0..100 {
+			let (p1, p2) = layout.generate_random_valid_swap(&mut rng)?;
+			layout.swap(p1, p2)?;
+			layout.verify_pathmap_correctness()?;
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_mod_tap_keys() -> Result<(), AlcError> {
+		let mut layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			A/SFT_10 B_10 _/CTRL_10 C_10
+		").unwrap();
+		layout.verify_pathmap_correctness()?;
+		let held_at = |layout_position| {
+			let mut sequence = LayoutPositionSequence::from_vector(vec![]);
+			sequence.push_hold(layout_position);
+			vec![sequence]
+		};
+		assert_eq!(layout.paths_to_keycode(_SFT)?, &held_at(LayoutPosition::new(0, 0, 0)));
+		assert_eq!(layout.paths_to_keycode(_CTRL)?, &held_at(LayoutPosition::new(0, 0, 2)));
+		assert_eq!(layout.paths_to_keycode(_A)?[0].holds(), &[] as &[usize]);
+		// the tapped keycodes move, the holds stay in place
+		assert!(layout.swap(LayoutPosition::new(0, 0, 0), LayoutPosition::new(0, 0, 2))?);
+		assert_eq!(layout[(0, 0, 0)].value(), _NO);
+		assert_eq!(layout[(0, 0, 0)].hold(), Some(_SFT));
+		assert_eq!(layout[(0, 0, 2)].value(), _A);
+		assert_eq!(layout[(0, 0, 2)].hold(), Some(_CTRL));
+		assert_eq!(layout.paths_to_keycode(_A)?, &vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 2)])]);
+		assert_eq!(layout.paths_to_keycode(_SFT)?, &held_at(LayoutPosition::new(0, 0, 0)));
+		layout.verify_pathmap_correctness()?;
+		Ok(())
+	}
+
+	#[test]
+	fn test_combos() -> Result<(), AlcError> {
+		let mut layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			A_10 B_10 C_10 D_10
+			LS1_10 E_10 F_10 G_10
+			___Layer 1___
+			H_10 I_10 J_10 K_10
+			__10 L_10 M_10 N_10
+		").unwrap();
+		let combos = vec![
+			Combo::try_new(vec![LayoutPosition::new(0, 0, 1), LayoutPosition::new(0, 0, 2)], KeycodeKey::try_from("ENT_00")?)?,
+			Combo::try_new(vec![LayoutPosition::new(1, 0, 1), LayoutPosition::new(1, 0, 2)], KeycodeKey::try_from("__10")?)?,
+		];
+		layout.set_combos(combos)?;
+		layout.verify_pathmap_correctness()?;
+		let mut expected = LayoutPositionSequence::from_vector(vec![]);
+		expected.push_combo(&[LayoutPosition::new(0, 0, 1), LayoutPosition::new(0, 0, 2)]);
+		assert_eq!(layout.paths_to_keycode(_ENT)?, &vec![expected]);
+		let bad_combo = Combo::try_new(vec![LayoutPosition::new(0, 0, 1), LayoutPosition::new(0, 0, 4)], KeycodeKey::try_from("TAB_00")?)?;
+		assert!(layout.clone().set_combos(vec![bad_combo]).is_err());
+
+		// the blank combo on layer 1 is reached through LS1
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		layout.randomize(&mut rng, &[_O, _P])?;
+		assert!(layout.combos()[1].value() == _O || layout.combos()[1].value() == _P);
+		let mut expected = LayoutPositionSequence::from_tuples(vec![(0, 1, 0)]);
+		expected.push_combo(&[LayoutPosition::new(1, 0, 1), LayoutPosition::new(1, 0, 2)]);
+		assert_eq!(layout.paths_to_keycode(layout.combos()[1].value())?, &vec![expected]);
+
+		// only moveable combos trade keycodes with keys
+		assert!(layout.swap_combo(0, LayoutPosition::new(0, 0, 0)).is_err());
+		let combo_value = layout.combos()[1].value();
+		assert!(layout.swap_combo(1, LayoutPosition::new(0, 0, 0))?);
+		assert_eq!(layout.combos()[1].value(), _A);
+		assert_eq!(layout[(0, 0, 0)].value(), combo_value);
+		assert!(!layout.swap_combo(1, LayoutPosition::new(0, 1, 0))?);
+		layout.verify_pathmap_correctness()?;
+		for _i in 0..100 {
+			if let Some((combo_index, p)) = layout.generate_random_valid_combo_swap(&mut rng) {
+				layout.swap_combo(combo_index, p)?;
+				layout.verify_pathmap_correctness()?;
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	fn test_repeat_and_magic_keys() -> Result<(), AlcError> {
+		let mut layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			L_10 E_10 REP_10 MAGIC_10
+		").unwrap();
+		layout.set_magic_rules(vec![
+			MagicRule::try_new(_E, KeycodeKey::try_from("Y_00")?)?,
+			MagicRule::try_new(_L, KeycodeKey::try_from("__10")?)?,
+		])?;
+		assert!(layout.set_magic_rules(vec![MagicRule::try_new(_E, KeycodeKey::try_from("Y_00")?)?, MagicRule::try_new(_E, KeycodeKey::try_from("A_00")?)?]).is_err());
+		// L L can also be typed with the repeat key
+		let seqs = layout.ngram_to_sequences(&Ngram::new(vec![_L, _L]))?;
+		assert_eq!(seqs, vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (0, 0, 0)]), LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (0, 0, 2)])]);
+		// Y is only typeable through the magic key after E
+		let seqs = layout.ngram_to_sequences(&Ngram::new(vec![_E, _Y]))?;
+		assert_eq!(seqs, vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 1), (0, 0, 3)])]);
+		assert!(layout.ngram_to_sequences(&Ngram::new(vec![_Y])).is_err());
+		assert!(layout.ngram_to_sequences(&Ngram::new(vec![_L, _Y])).is_err());
+
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		layout.randomize(&mut rng, &[_A, _S])?;
+		let magic_l = layout.magic_output(_L).unwrap();
+		assert!(magic_l == _A || magic_l == _S);
+		assert!(layout.replace_magic(0, _A).is_err());
+		assert!(!layout.replace_magic(1, magic_l)?);
+		assert!(layout.replace_magic(1, _D)?);
+		assert_eq!(layout.ngram_to_sequences(&Ngram::new(vec![_L, _D]))?, vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (0, 0, 3)])]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_valid_swaps() {
+		let layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			A_10 B_11 C_11 LS1_10
+			___Layer 1___
+			D_10 E_00 H_10 LST1_0_10
+		").unwrap();
+		let swaps = layout.valid_swaps();
+		// symmetric keys go first, layer switches only swap within their layer and never with symmetric keys
+		assert!(swaps.contains(&(LayoutPosition::new(0, 0, 1), LayoutPosition::new(0, 0, 0))));
+		assert!(swaps.contains(&(LayoutPosition::new(0, 0, 3), LayoutPosition::new(0, 0, 0))));
+		assert!(!swaps.contains(&(LayoutPosition::new(0, 0, 3), LayoutPosition::new(0, 0, 1))));
+		assert!(!swaps.iter().any(|(p1, p2)| *p1 == LayoutPosition::new(1, 0, 1) || *p2 == LayoutPosition::new(1, 0, 1)));
+		assert!(!swaps.iter().any(|(p1, p2)| *p1 == LayoutPosition::new(1, 0, 3) || *p2 == LayoutPosition::new(1, 0, 3)));
+		for (p1, p2) in swaps {
+			let mut layout_clone = layout.clone();
+			layout_clone.swap(p1, p2).unwrap();
+		}
+	}
+
+	#[test]
+	fn test_regions() {
+		let mut rng = ChaCha8Rng::seed_from_u64(1);
+		let mut layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			A_101 E_101 __101 B_10
+			C_10 ENT_102 __102 __10
+		").unwrap();
+		layout.randomize(&mut rng, &[_F, _G, _H, _I]).unwrap();
+		let region_positions = |layout: &Layout<2, 4>, region: Option<u8>| -> Vec<(usize, usize)> {
+			let mut positions = vec![];
+			for row_index in 0..2 {
+				for col_index in 0..4 {
+					if layout[(0, row_index, col_index)].region() == region {
+						positions.push((row_index, col_index));
+					}
+				}
+			}
+			positions
+		};
+		assert_eq!(region_positions(&layout, Some(1)), vec![(0, 0), (0, 1), (0, 2)]);
+		assert_eq!(region_positions(&layout, Some(2)), vec![(1, 1), (1, 2)]);
+		for (p1, p2) in layout.valid_swaps() {
+			assert_eq!(layout[p1].region(), layout[p2].region());
+		}
+		for _i in 0..50 {
+			let (p1, p2) = layout.generate_random_valid_swap(&mut rng).unwrap();
+			layout.swap(p1, p2).unwrap();
+			if let Some(p) = layout.generate_valid_replace_position(&mut rng) {
+				assert_eq!(layout[p].region(), None);
+			}
+		}
+		assert_eq!(region_positions(&layout, Some(1)), vec![(0, 0), (0, 1), (0, 2)]);
+		assert_eq!(region_positions(&layout, Some(2)), vec![(1, 1), (1, 2)]);
+		let ent_position = layout.positions_of(_ENT)[0];
+		assert!(ent_position == LayoutPosition::new(0, 1, 1) || ent_position == LayoutPosition::new(0, 1, 2));
+		assert_eq!(Layout::<2, 4>::try_from(format!("{:b}", layout).as_str()).unwrap(), layout);
+		assert_eq!(Layout::<1, 3>::try_from("A_101 B_103 C_101"), Err(AlcError::LayoutRegionError(3)));
+	}
+
 	#[test]
 	fn test_replace() -> Result<(), AlcError> {
 		let mut layout = Layout::<1, 4>::try_from("
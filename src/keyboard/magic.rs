@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::alc_error::AlcError;
+use crate::text_processor::keycode::Keycode::{self, *};
+use super::key::{KeyValue, KeycodeKey};
+
+/// Says what the magic (alt-repeat) key types after `previous`. The key uses the usual {keycode}_{moveability flag}{symmetry flag} format; a moveable rule lets the optimizer choose what the magic key types after `previous`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MagicRule {
+	previous: Keycode,
+	key: KeycodeKey,
+}
+impl MagicRule {
+	pub fn try_new(previous: Keycode, key: KeycodeKey) -> Result<Self, AlcError> {
+		let rule_string = format!("{} -> {}", previous, format!("{:b}", key).trim());
+		if previous == _NO || previous == _REP || previous == _MAGIC {
+			return Err(AlcError::InvalidMagicRuleError(rule_string, String::from("the previous keycode must be something the magic key can follow")));
+		}
+		if key.is_symmetric() || key.region().is_some() || key.hold().is_some() {
+			return Err(AlcError::InvalidMagicRuleError(rule_string, String::from("magic keys can't be symmetric, in a region or mod-tap")));
+		}
+		if key.value().is_layer_switch() || matches!(key.value(), _LST(_, _) | _REP | _MAGIC) {
+			return Err(AlcError::InvalidMagicRuleError(rule_string, String::from("the magic key can't switch layers or repeat")));
+		}
+		Ok(MagicRule { previous, key })
+	}
+	pub fn previous(&self) -> Keycode {
+		self.previous
+	}
+	pub fn key(&self) -> &KeycodeKey {
+		&self.key
+	}
+	pub fn set_value(&mut self, new_value: Keycode) {
+		self.key.set_value(new_value)
+	}
+}
+impl KeyValue for MagicRule {
+	type Item = Keycode;
+	fn value(&self) -> Self::Item {
+		self.key.value()
+	}
+}
+impl fmt::Display for MagicRule {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} -> {}", str::replace(&self.previous.to_string(), "_", ""), self.key.to_string().trim())
+	}
+}
@@ -2,13 +2,137 @@
 // use rand::SeedableRng;
 // use rand_chacha::ChaCha8Rng;
 // use alc::{objective::scoring::AdvancedScoreFunction, optimizer::{optimize_from_toml, LayoutOptimizer}};
-use alc::optimizer::optimize_from_toml;
-use clap::Parser;
+use alc::{alc_error::AlcError, optimizer::{metrics::{compare_from_toml, comparison_rows, format_comparison_csv, format_comparison_table}, optimize_from_toml}, text_processor::dataset::{dataset_cache_dir, list_cached_datasets, purge_cached_datasets, FrequencyDataset}};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-	#[arg(short, long)]
-	config: String,
+	/// Optimizer config to run
+	#[arg(short, long, required = true)]
+	config: Option<String>,
+	#[command(subcommand)]
+	command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+	/// Manage processed datasets cached in the user's cache directory
+	Cache {
+		#[command(subcommand)]
+		action: CacheAction,
+	},
+	/// Inspect cached datasets, given either a path or a file name from `alc cache list`
+	Dataset {
+		#[command(subcommand)]
+		action: DatasetAction,
+	},
+	/// Score layouts side by side using the datasets and score options of the first config
+	Compare {
+		/// Optimizer configs whose layouts are compared, e.g., a current layout and optimizer results
+		#[arg(required = true)]
+		configs: Vec<String>,
+		#[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+		format: OutputFormat,
+	},
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+	/// Aligned table with the best value of each metric marked with *
+	Table,
+	Csv,
+	Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum DatasetAction {
+	/// Print the most frequent n-grams of each size with their counts and percentages of the n-grams kept in the dataset
+	Show {
+		dataset: String,
+		/// Number of n-grams to print per size
+		#[arg(short, long, default_value_t = 20)]
+		top: usize,
+	},
+	/// Print the n-grams whose relative frequency differs the most between two datasets, relative to the n-grams kept in each
+	Diff {
+		dataset_a: String,
+		dataset_b: String,
+		/// Number of n-grams to print per size
+		#[arg(short, long, default_value_t = 20)]
+		top: usize,
+	},
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+	/// List cached datasets
+	List,
+	/// Delete cached datasets
+	Purge {
+		/// Only delete datasets made from directories with this name
+		name: Option<String>,
+	},
+}
+
+fn run_cache_action(action: CacheAction) -> Result<(), AlcError> {
+	let cache_dir = dataset_cache_dir()?;
+	match action {
+		CacheAction::List => {
+			println!("{}", cache_dir.display());
+			for (path, size) in list_cached_datasets(&cache_dir)? {
+				println!("{}\t{} bytes", path.file_name().unwrap().to_string_lossy(), size);
+			}
+		},
+		CacheAction::Purge { name } => {
+			let purged = purge_cached_datasets(&cache_dir, name.as_deref())?;
+			println!("deleted {} cached dataset(s)", purged.len());
+		},
+	}
+	Ok(())
+}
+
+fn run_dataset_action(action: DatasetAction) -> Result<(), AlcError> {
+	match action {
+		DatasetAction::Show { dataset, top } => {
+			let dataset = FrequencyDataset::try_from_cache_file(&dataset)?;
+			for n in dataset.ngram_sizes() {
+				println!("___{}-grams___ (% of the kept {}-grams)", n, n);
+				for (ngram, count, frequency) in dataset.top_ngrams(n, top) {
+					println!("{}: {} ({:.3}%)", ngram, count, frequency * 100.0);
+				}
+			}
+		},
+		DatasetAction::Diff { dataset_a, dataset_b, top } => {
+			let dataset_a = FrequencyDataset::try_from_cache_file(&dataset_a)?;
+			let dataset_b = FrequencyDataset::try_from_cache_file(&dataset_b)?;
+			let mut ngram_sizes = dataset_a.ngram_sizes();
+			ngram_sizes.extend(dataset_b.ngram_sizes());
+			ngram_sizes.sort_unstable();
+			ngram_sizes.dedup();
+			for n in ngram_sizes {
+				println!("___{}-grams___ ({} vs. {}, % of the kept {}-grams in each)", n, dataset_a.name, dataset_b.name, n);
+				for (ngram, frequency_a, frequency_b) in dataset_a.diff(&dataset_b, n, top) {
+					println!("{}: {:.3}% vs. {:.3}% ({:+.3}%)", ngram, frequency_a * 100.0, frequency_b * 100.0, (frequency_a - frequency_b) * 100.0);
+				}
+			}
+		},
+	}
+	Ok(())
+}
+
+fn run_compare(configs: Vec<String>, format: OutputFormat) -> Result<(), AlcError> {
+	let metrics = compare_from_toml(&configs)?;
+	match format {
+		OutputFormat::Table => print!("{}", format_comparison_table(&configs, &comparison_rows(&metrics))),
+		OutputFormat::Csv => print!("{}", format_comparison_csv(&configs, &comparison_rows(&metrics))),
+		OutputFormat::Json => {
+			let named_metrics = configs.iter().zip(metrics.iter()).map(|(config, metrics)| serde_json::json!({ "config": config, "metrics": metrics })).collect::<Vec<_>>();
+			let json = serde_json::to_string_pretty(&named_metrics).map_err(|e| AlcError::GenericError(e.to_string()))?;
+			println!("{}", json);
+		},
+	}
+	Ok(())
 }
 
 // fn main() {
@@ -24,7 +148,13 @@ fn main() {
 	// cargo flamegraph --bin=alc --palette=rust --output=performance/0_1_1.svg
 	
 	let args = Args::parse();
-	match optimize_from_toml(args.config) {
+	let result = match args.command {
+		Some(Command::Cache { action }) => run_cache_action(action),
+		Some(Command::Dataset { action }) => run_dataset_action(action),
+		Some(Command::Compare { configs, format }) => run_compare(configs, format),
+		None => optimize_from_toml(args.config.unwrap()).map(|_| ()),
+	};
+	match result {
 		Ok(_) => (),
 		Err(e) => println!("{}", e),
 	}
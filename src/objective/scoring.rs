@@ -8,6 +8,7 @@ use crate::text_processor::keycode::Keycode::*;
 pub trait Score<const R: usize, const C: usize> {
 	fn new() -> Self;
 
+	/// Removes layer key presses that aren't needed when consecutive keys are on the same layer, i.e., a held layer switch or layer-tap stays held and a toggled layer stays toggled on. One-shot layers only apply to one key so they are never removed.
 	fn cancel_layer_switches(&self, layout: &Layout<R, C>, layout_position_sequence: LayoutPositionSequence) -> LayoutPositionSequence;
 
 	fn score_small(&self, effort_layer: &Layer<R, C, f64>, phalanx_layer: &Layer<R, C, PhalanxKey>, layout_position_sequence: LayoutPositionSequence, config: &LayoutOptimizerConfig) -> Option<f64>;
@@ -86,15 +87,42 @@ impl<const R: usize, const C: usize> Score<R, C> for AdvancedScoreFunction {
 	fn cancel_layer_switches(&self, layout: &Layout<R, C>, layout_position_sequence: LayoutPositionSequence) -> LayoutPositionSequence {
 		let mut new_sequence: Vec<LayoutPosition> = Default::default();
 
+		let positions: Vec<LayoutPosition> = layout_position_sequence.into_iter().collect();
 		let mut imminent_layer = 0;
-		for lp in layout_position_sequence {
-			if let _LS(n) = layout[lp].value() {
-				if n == imminent_layer {
+		let mut pending_toggle_off: Option<LayoutPosition> = None;
+		for (i, lp) in positions.iter().enumerate() {
+			let key_value = layout[*lp].value();
+			if let Some(toggle_off) = pending_toggle_off.take() {
+				// toggling a layer off and then straight back on can be skipped, so a toggle is only paid for once over a run of keys
+				if key_value == _TG(toggle_off.layer_index) && lp.row_index == toggle_off.row_index && lp.col_index == toggle_off.col_index {
 					continue;
 				}
-				imminent_layer = n;
+				new_sequence.push(toggle_off);
 			}
-			new_sequence.push(lp);
+			match key_value {
+				_LS(n) => {
+					if n == imminent_layer {
+						continue;
+					}
+					imminent_layer = n;
+				},
+				// a layer-tap is only held if the next key is on its layer, otherwise it was tapped
+				_LTAP(n) if positions.get(i + 1).is_some_and(|next_lp| next_lp.layer_index == n) => {
+					if n == imminent_layer {
+						continue;
+					}
+					imminent_layer = n;
+				},
+				_LST(n, source_layer) if layout[(source_layer, lp.row_index, lp.col_index)].value() == _TG(n) => {
+					pending_toggle_off = Some(*lp);
+					continue;
+				},
+				_ => (),
+			}
+			new_sequence.push(*lp);
+		}
+		if let Some(toggle_off) = pending_toggle_off {
+			new_sequence.push(toggle_off);
 		}
 		LayoutPositionSequence::from_vector(new_sequence)
 	}
@@ -173,7 +201,11 @@ impl<const R: usize, const C: usize> Score<R, C> for AdvancedScoreFunction {
 	}
 
 	fn score_layout_position_sequence(&self, layout: &Layout<R, C>, effort_layer: &Layer<R, C, f64>, phalanx_layer: &Layer<R, C, PhalanxKey>, base_layout_position_sequence: LayoutPositionSequence, config: &LayoutOptimizerConfig) -> f64 {
-		let layout_position_sequence = self.cancel_layer_switches(layout, base_layout_position_sequence);
+		// before the other steps, since they drop which mod-tap keys are held
+		let mod_tap_score = score_mod_tap_misfires(layout, effort_layer, phalanx_layer, &base_layout_position_sequence, config.score_options.mod_tap_misfire_penalty_factor);
+		let (layout_position_sequence, combo_score) = separate_combos(effort_layer, base_layout_position_sequence, config.score_options.combo_penalty_factor);
+		let layout_position_sequence = self.cancel_layer_switches(layout, layout_position_sequence);
+		let (layout_position_sequence, tapped_modifier_score) = separate_tapped_modifiers(layout, effort_layer, layout_position_sequence);
 		// during debug, check that the position preceeding a higher layer position is a layer switch
 		// we can use the fact that layer switches always should occur before a higher layer position to eliminate the need to actually check the layout for layer switches, and simplify checking when layer switches can be canceled
 		let debug_clone = layout_position_sequence.clone();
@@ -188,7 +220,7 @@ impl<const R: usize, const C: usize> Score<R, C> for AdvancedScoreFunction {
 		let roll_reduction = config.score_options.finger_roll_reduction_factor;
 
 		if let Some(v) = self.score_small(effort_layer, phalanx_layer, layout_position_sequence.clone(), config) {
-			return v;
+			return v + tapped_modifier_score + mod_tap_score + combo_score;
 		}
 
 
@@ -335,7 +367,7 @@ impl<const R: usize, const C: usize> Score<R, C> for AdvancedScoreFunction {
 		}
 		
 		score += efforts.iter().sum::<f64>() + reductions.iter().sum::<f64>();
-		score
+		score + tapped_modifier_score + mod_tap_score + combo_score
 	}
 }
 
@@ -347,6 +379,50 @@ pub fn calculate_final_reduction(initial_reduction: f64, _n: usize, weight: f64)
 	1.0 - (1.0 - initial_reduction) * weight
 }
 
+/// One-shot shift (_OSS) and caps word (_CAPW) are tapped and released before the keys they apply to, unlike a held _SFT. So they only cost their own effort and don't take part in same finger, alternation or roll scoring of the rest of the sequence
+fn separate_tapped_modifiers<const R: usize, const C: usize>(layout: &Layout<R, C>, effort_layer: &Layer<R, C, f64>, layout_position_sequence: LayoutPositionSequence) -> (LayoutPositionSequence, f64) {
+	let mut tapped_modifier_score = 0.0;
+	let mut remaining_sequence: Vec<LayoutPosition> = vec![];
+	for lp in layout_position_sequence {
+		match layout[lp].value() {
+			_OSS | _CAPW => tapped_modifier_score += effort_layer[lp],
+			_ => remaining_sequence.push(lp),
+		}
+	}
+	(LayoutPositionSequence::from_vector(remaining_sequence), tapped_modifier_score)
+}
+
+/// Combo keys are pressed together, so each combo is a single press with the effort of its hardest key times `penalty_factor`. Combos don't take part in same finger, alternation or roll scoring of the rest of the sequence
+fn separate_combos<const R: usize, const C: usize>(effort_layer: &Layer<R, C, f64>, layout_position_sequence: LayoutPositionSequence, penalty_factor: f64) -> (LayoutPositionSequence, f64) {
+	let mut combo_score = 0.0;
+	for (start, end) in layout_position_sequence.combos() {
+		let max_effort = layout_position_sequence.iter().skip(*start).take(end - start).map(|lp| effort_layer[*lp]).fold(0.0, f64::max);
+		combo_score += max_effort * penalty_factor;
+	}
+	let combos = layout_position_sequence.combos().to_vec();
+	let remaining_sequence = layout_position_sequence.into_iter().enumerate().filter(|(i, _lp)| !combos.iter().any(|(start, end)| i >= start && i < end)).map(|(_i, lp)| lp).collect();
+	(LayoutPositionSequence::from_vector(remaining_sequence), combo_score)
+}
+
+/// A tapped mod-tap key followed by a different finger on the same hand is a fast roll, which is where the tap is likely to be read as the hold. Held mod-tap keys are pressed on purpose before the key they modify, so they can't misfire this way. Combo keys are skipped, same as in the rest of the scoring. Returns the extra effort of the mod-tap keys in such rolls
+fn score_mod_tap_misfires<const R: usize, const C: usize>(layout: &Layout<R, C>, effort_layer: &Layer<R, C, f64>, phalanx_layer: &Layer<R, C, PhalanxKey>, layout_position_sequence: &LayoutPositionSequence, penalty_factor: f64) -> f64 {
+	let mut mod_tap_score = 0.0;
+	let combos = layout_position_sequence.combos();
+	let positions = layout_position_sequence.iter().enumerate().filter(|(i, _lp)| !combos.iter().any(|(start, end)| i >= start && i < end)).collect::<Vec<(usize, &LayoutPosition)>>();
+	for pair in positions.windows(2) {
+		let ((i1, lp1), (_i2, lp2)) = (pair[0], pair[1]);
+		if layout[*lp1].hold().is_none() || layout_position_sequence.holds().contains(&i1) {
+			continue;
+		}
+		let (hand1, finger1) = phalanx_layer[*lp1].value();
+		let (hand2, finger2) = phalanx_layer[*lp2].value();
+		if hand1 == hand2 && finger1 != finger2 {
+			mod_tap_score += (penalty_factor - 1.0) * effort_layer[*lp1];
+		}
+	}
+	mod_tap_score
+}
+
 fn same_hand_and_finger(current_hand: Hand, previous_hand: Hand, current_finger: Finger, previous_finger: Finger) -> bool {
 	current_hand == previous_hand && current_finger == previous_finger
 }
@@ -356,7 +432,8 @@ fn same_hand_and_finger(current_hand: Hand, previous_hand: Hand, current_finger:
 mod tests {
 	
 
-	use crate::keyboard::LayoutPosition;
+	use crate::keyboard::{combo::Combo, key::KeycodeKey, LayoutPosition};
+	use crate::text_processor::ngram::Ngram;
 	use super::*;
 
 	#[test]
@@ -422,6 +499,128 @@ mod tests {
 		
 	}
 
+	#[test]
+	fn test_toggle_one_shot_and_layer_tap_cancels() {
+		let layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			TG1_10 OSL2_10 LT3:SPC_10 T_10
+
+			___Layer 1___
+			__10 H_10 E_10 R_10
+
+			___Layer 2___
+			A_10 __10 B_10 C_10
+
+			___Layer 3___
+			D_10 F_10 __10 G_10
+		").unwrap();
+		let sf = AdvancedScoreFunction{};
+
+		// toggle: on, H, off, on, E, off -> on, H, E, off
+		let her = layout.ngram_to_sequences(&Ngram::new(vec![_H, _E])).unwrap();
+		assert_eq!(her, vec![LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (1, 0, 1), (1, 0, 0), (0, 0, 0), (1, 0, 2), (1, 0, 0)])]);
+		assert_eq!(sf.cancel_layer_switches(&layout, her[0].clone()), LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (1, 0, 1), (1, 0, 2), (1, 0, 0)]));
+
+		// one-shot layers are pressed again for every key
+		let ab = layout.ngram_to_sequences(&Ngram::new(vec![_A, _B])).unwrap();
+		assert_eq!(sf.cancel_layer_switches(&layout, ab[0].clone()), LayoutPositionSequence::from_tuples(vec![(0, 0, 1), (2, 0, 0), (0, 0, 1), (2, 0, 2)]));
+
+		// a held layer-tap stays held, but tapping it doesn't count as holding it
+		let dfspc = layout.ngram_to_sequences(&Ngram::new(vec![_D, _F, _SPC, _T])).unwrap();
+		assert_eq!(sf.cancel_layer_switches(&layout, dfspc[0].clone()), LayoutPositionSequence::from_tuples(vec![(0, 0, 2), (3, 0, 0), (3, 0, 1), (0, 0, 2), (0, 0, 3)]));
+	}
+
+	#[test]
+	fn test_one_shot_shift() {
+		let layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			SFT_10 H_10 OSS_10 T_10
+		").unwrap();
+		let effort_layer = Layer::<1, 4, f64>::try_from("
+			0.1 0.2 0.3 0.4
+		").unwrap();
+		let phalanx_layer = Layer::<1, 4, PhalanxKey>::try_from("
+			l:m l:m l:m r:r
+		").unwrap();
+		let sf = AdvancedScoreFunction{};
+		let config = LayoutOptimizerConfig::default();
+
+		// held shift and the key it shifts are on the same finger
+		let held = LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (0, 0, 1)]);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, held, &config);
+		assert_eq!(score, 0.1 + 0.2 * config.score_options.same_finger_penalty_factor);
+
+		// one-shot shift is on the same finger too, but it's tapped beforehand so there is no same finger penalty
+		let one_shot = LayoutPositionSequence::from_tuples(vec![(0, 0, 2), (0, 0, 1)]);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, one_shot, &config);
+		assert_eq!(score, 0.2 + 0.3);
+	}
+
+	#[test]
+	fn test_mod_tap_misfire() {
+		let layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			A/SFT_10 B_10 C_10 D/CTRL_10
+		").unwrap();
+		let effort_layer = Layer::<1, 4, f64>::try_from("
+			0.1 0.2 0.3 0.4
+		").unwrap();
+		let phalanx_layer = Layer::<1, 4, PhalanxKey>::try_from("
+			l:p l:r r:m r:i
+		").unwrap();
+		let sf = AdvancedScoreFunction{};
+		let mut config = LayoutOptimizerConfig::default();
+		config.score_options.mod_tap_misfire_penalty_factor = 2.0;
+
+		// A/SFT rolls into B on the same hand
+		let roll = LayoutPositionSequence::from_tuples(vec![(0, 0, 0), (0, 0, 1)]);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, roll, &config);
+		assert_eq!(score, 0.1 * 2.0 + 0.2);
+
+		// holding A/SFT to shift B is on purpose, so there is no misfire risk
+		let mut hold = LayoutPositionSequence::from_vector(vec![]);
+		hold.push_hold(LayoutPosition::new(0, 0, 0));
+		hold.push(LayoutPosition::new(0, 0, 1));
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, hold, &config);
+		assert_eq!(score, 0.1 + 0.2);
+		let shifted_b = layout.ngram_to_sequences(&Ngram::new(vec![_SFT, _B])).unwrap();
+		assert_eq!(sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, shifted_b[0].clone(), &config), 0.1 + 0.2);
+
+		// D/CTRL is followed by the other hand, so there is no misfire risk
+		let alternation = LayoutPositionSequence::from_tuples(vec![(0, 0, 3), (0, 0, 0)]);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, alternation, &config);
+		assert_eq!(score, 0.4 + 0.1);
+	}
+
+	#[test]
+	fn test_combos() {
+		let mut layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			A_10 B_10 C_10 D_10
+		").unwrap();
+		layout.set_combos(vec![Combo::try_new(vec![LayoutPosition::new(0, 0, 1), LayoutPosition::new(0, 0, 2)], KeycodeKey::try_from("E_10").unwrap()).unwrap()]).unwrap();
+		let effort_layer = Layer::<1, 4, f64>::try_from("
+			0.1 0.2 0.3 0.4
+		").unwrap();
+		let phalanx_layer = Layer::<1, 4, PhalanxKey>::try_from("
+			l:m l:i r:i r:m
+		").unwrap();
+		let sf = AdvancedScoreFunction{};
+		let config = LayoutOptimizerConfig::default();
+
+		let e = layout.ngram_to_sequences(&Ngram::new(vec![_E])).unwrap();
+		assert_eq!(e.len(), 1);
+		assert_eq!(e[0].press_count(), 1);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, e[0].clone(), &config);
+		assert_eq!(score, 0.3 * config.score_options.combo_penalty_factor);
+
+		// the combo is scored on its own and the rest of the sequence as usual
+		let ae = layout.ngram_to_sequences(&Ngram::new(vec![_A, _E])).unwrap();
+		assert_eq!(ae[0].combos(), &[(1, 3)]);
+		let score = sf.score_layout_position_sequence(&layout, &effort_layer, &phalanx_layer, ae[0].clone(), &config);
+		assert_eq!(score, 0.1 + 0.3 * config.score_options.combo_penalty_factor);
+	}
+
 	#[test]
 	fn test_alternating() {
 		let layout = Layout::<1, 4>::init_blank(1);
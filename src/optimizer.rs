@@ -1,4 +1,6 @@
 pub mod config;
+pub mod constraint;
+pub mod metrics;
 pub mod optimizer_presets;
 
 use std::collections::HashSet;
@@ -12,6 +14,7 @@ use rand::prelude::*;
 use rand::Rng;
 use rand_chacha::ChaCha8Rng;
 use rayon::iter::IndexedParallelIterator;
+use rayon::iter::IntoParallelIterator;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
 use tqdm::tqdm;
@@ -24,15 +27,16 @@ use crate::keyboard::layout_presets::LayoutSizePresets;
 use crate::keyboard::LayoutPosition;
 use crate::keyboard::LayoutPositionSequence;
 use crate::keyboard::{layout::*, layer::*};
-use crate::optimizer::ngram::Ngram;
 use crate::text_processor::*;
 use crate::objective::scoring::*;
 
 use self::config::LayoutOptimizerConfig;
 use self::config::LayoutOptimizerTomlAdapter;
+use self::constraint::check_constraints;
 use self::dataset::FrequencyDataset;
-use self::frequency_holder::{SingleGramFrequencies, TopFrequenciesToTake::*};
+use self::frequency_holder::{Frequencies, SingleGramFrequencies};
 use self::keycode::generate_default_keycode_set;
+use self::ngram::Ngram;
 
 #[derive(Debug)]
 pub struct OperationCounter {
@@ -64,6 +68,13 @@ fn write_text_to_file(filename: &str, text: String) -> Result<(), AlcError> {
 
 }
 
+/// An island's own rng and its current population, see [LayoutOptimizer::evolve_islands]
+type Population<const R: usize, const C: usize> = Vec<(Layout<R, C>, f64)>;
+type Island<const R: usize, const C: usize> = (ChaCha8Rng, Population<R, C>);
+type GenerationTimes = (f64, f64, f64); // take best, generate, and score times
+
+type PositionSwaps = Vec<(LayoutPosition, LayoutPosition)>;
+
 pub trait LO {}
 impl<const R: usize, const C: usize, S> LO for LayoutOptimizer<R, C, S> where S: Score<R, C> + Send + Sync {}
 
@@ -84,10 +95,13 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		}
 	}
 
-	pub fn compute_datasets(&self) -> Result<Vec<FrequencyDataset<u32>>, AlcError> {
-		let mut datasets: Vec<FrequencyDataset<u32>> = Default::default();
-		for path in &self.config.dataset_options.dataset_paths {
-			let f = FrequencyDataset::<u32>::try_from_dir(path.as_str(), self.config.dataset_options.max_ngram_size, Num(self.config.dataset_options.top_n_ngrams_to_take), &self.config.keycode_options)?;
+	/// f64 so that counted datasets and tables of fractional frequencies can be scored together, see [FrequencyDataset::try_from_path]
+	pub fn compute_datasets(&self) -> Result<Vec<FrequencyDataset<f64>>, AlcError> {
+		let mut datasets: Vec<FrequencyDataset<f64>> = Default::default();
+		let dataset_options = &self.config.dataset_options;
+		let top_frequencies_to_take = dataset_options.top_frequencies_to_take()?;
+		for path in &dataset_options.dataset_paths {
+			let f = FrequencyDataset::try_from_path(path.as_str(), dataset_options.max_ngram_size, &top_frequencies_to_take, &self.config.keycode_options, &dataset_options.extraction_options())?;
 			datasets.push(f);
 		}
 		Ok(datasets)
@@ -103,42 +117,28 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		println!("initial valid keycodes {:?}", self.config.valid_keycodes);
 	}
 
-	fn score_single_grams(&self, layout: &Layout<R, C>, frequencies: SingleGramFrequencies<u32>, save_positions: bool) -> Result<(f64, HashSet<LayoutPosition>), AlcError> {
+	/// The sequence the score function picks to type `ngram`, the one with the lowest score after the extra length penalty, along with that score
+	pub(crate) fn best_sequence(&self, layout: &Layout<R, C>, ngram: &Ngram) -> Result<(LayoutPositionSequence, f64), AlcError> {
+		let mut sequences = layout.ngram_to_sequences(ngram)?;
+		let scores = sequences.iter().map(|sequence| {
+			self.score_function.score_layout_position_sequence(layout, &self.effort_layer, &self.phalanx_layer, sequence.clone(), &self.config) * self.config.score_options.extra_length_penalty_factor.powf((sequence.press_count() - ngram.len()) as f64)
+		}).collect::<Vec<f64>>();
+		// let min_index = match possible_scores.iter().enumerate().min_by(|(_, a), (_, b)| a.total_cmp(b)).map(|(idx, _)| idx) {
+		// 	Some(v) => v,
+		// 	None => 0,
+		// };
+		let min_index = arg_min(&scores)?;
+		Ok((sequences.swap_remove(min_index), scores[min_index]))
+	}
+
+	fn score_single_grams<T>(&self, layout: &Layout<R, C>, frequencies: &SingleGramFrequencies<T>, save_positions: bool) -> Result<(f64, HashSet<LayoutPosition>), AlcError> where T: Frequencies {
 		let mut score: f64 = 0.0;
 		let total = frequencies.total;
 		let mut visited_positions: HashSet<LayoutPosition> = HashSet::default();
-		let effort_layer = &self.effort_layer;
-		let phalanx_layer = &self.phalanx_layer;
-		for (ngram, ngram_frequency) in frequencies {
-			let ngram_len = ngram.len();
-			let sequences = layout.ngram_to_sequences(&ngram)?;
-			// let sequences = match layout.ngram_to_sequences(&ngram) {
-			// 	Some(v) => v,
-			// 	None => return Err(AlcError::UntypeableNgramError(ngram)),
-			// 	// return 0.0
-			// };
-			let mut possible_scores: Vec<f64> = vec![];
-			let mut possible_sequences: Vec<LayoutPositionSequence> = vec![];
-			for sequence in sequences {
-				let sequence_len = sequence.len();
-				if save_positions {
-					possible_sequences.push(sequence.clone());
-				}
-				let sequence_score = self.score_function.score_layout_position_sequence(layout, effort_layer, phalanx_layer, sequence, &self.config) * self.config.score_options.extra_length_penalty_factor.powf((sequence_len - ngram_len) as f64);
-				possible_scores.push(sequence_score);
-			}
-			
-
-			// let min_index = match possible_scores.iter().enumerate().min_by(|(_, a), (_, b)| a.total_cmp(b)).map(|(idx, _)| idx) {
-			// 	Some(v) => v,
-			// 	None => 0,
-			// };
-			let min_index = arg_min(&possible_scores)?;
-			let min_score = possible_scores[min_index];
-			
+		for (ngram, ngram_frequency) in frequencies.iter() {
+			let (min_sequence, min_score) = self.best_sequence(layout, ngram)?;
 			if save_positions {
-				let min_sequence = &possible_sequences[min_index];
-				for pos in min_sequence.clone() {
+				for pos in min_sequence {
 					visited_positions.insert(pos);
 				}
 			}
@@ -149,23 +149,61 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 			// println!("{} * {} / {}", min_score, ngram_frequency, total);
 			
 			
-			score += min_score * (ngram_frequency as f64) / total; // should be slightly more efficient to precompute counts / total, but lazy for now
+			score += min_score * ngram_frequency.to_f64() / total; // should be slightly more efficient to precompute counts / total, but lazy for now
 		}
 		Ok((score, visited_positions))
 	}
 
-	pub fn score_datasets(&self, layout: &Layout<R, C>, datasets: &[FrequencyDataset<u32>], save_positions: bool) -> Result<(f64, HashSet<LayoutPosition>), AlcError> {
+	fn check_dataset_weights<T>(&self, datasets: &[FrequencyDataset<T>]) -> Result<(), AlcError> where T: Frequencies {
+		let dataset_options = &self.config.dataset_options;
+		if datasets.len() != dataset_options.dataset_weights.len() {
+			return Err(AlcError::DatasetWeightsMismatchError(datasets.len(), dataset_options.dataset_weights.len()));
+		}
+		if !dataset_options.ngram_size_weights.is_empty() && dataset_options.ngram_size_weights.len() != dataset_options.max_ngram_size {
+			return Err(AlcError::NgramSizeWeightsMismatchError(dataset_options.ngram_size_weights.len(), dataset_options.max_ngram_size));
+		}
+		if let Some(w) = dataset_options.ngram_size_weights.iter().find(|w| **w < 0.0) {
+			return Err(AlcError::NegativeNgramSizeWeightError(*w));
+		}
+		if !dataset_options.ngram_size_weights.is_empty() && dataset_options.ngram_size_weights.iter().sum::<f64>() == 0.0 {
+			return Err(AlcError::ZeroNgramSizeWeightsError);
+		}
+		if !dataset_options.ngram_size_weights.is_empty() {
+			if let Some(dataset) = datasets.iter().find(|dataset| self.ngram_size_weights_total(dataset) == 0.0) {
+				return Err(AlcError::DatasetZeroNgramSizeWeightsError(dataset.name.clone()));
+			}
+		}
+		Ok(())
+	}
+
+	/// Sum of the `ngram_size_weights` of the n-gram sizes `dataset` has, so that a dataset missing some sizes, e.g., a table without 4-grams, still adds up to its full weight
+	fn ngram_size_weights_total<T>(&self, dataset: &FrequencyDataset<T>) -> f64 where T: Frequencies {
+		let ngram_size_weights = &self.config.dataset_options.ngram_size_weights;
+		dataset.ngram_frequencies.keys().filter_map(|ngram_size| ngram_size_weights.get(ngram_size - 1)).sum()
+	}
+
+	/// Generic over the frequency type, so blended f64 datasets score the same as the separate datasets with `dataset_weights`
+	pub fn score_datasets<T>(&self, layout: &Layout<R, C>, datasets: &[FrequencyDataset<T>], save_positions: bool) -> Result<(f64, HashSet<LayoutPosition>), AlcError> where T: Frequencies {
 		let mut score: f64 = 0.0;
 		let mut visited_positions: HashSet<LayoutPosition> = HashSet::default();
 		// let mut d_ind: usize = 0;
 		let dataset_weights_total: f64 = self.config.dataset_options.dataset_weights.iter().sum();
 		let weights_scaled = self.config.dataset_options.dataset_weights.iter().map(|x| x / dataset_weights_total).collect::<Vec<f64>>();
+		let ngram_size_weights = &self.config.dataset_options.ngram_size_weights;
 		for (d_ind, dataset) in datasets.iter().enumerate() {
-			let ngram_ratio = 1.0 / dataset.ngram_frequencies.len() as f64;
 			let mut dataset_score = 0.0;
+			let ngram_size_weights_total = self.ngram_size_weights_total(dataset);
 			for ngram_size in dataset.ngram_frequencies.keys() {
-				let (calculated_score, calculated_positions) = self.score_single_grams(layout, dataset.ngram_frequencies[ngram_size].clone(), save_positions)?; // this clone might be expensive
-				dataset_score += calculated_score * ngram_ratio; // each ngram length has equal weight to score, can change in the future
+				// each ngram length has equal weight to score unless ngram_size_weights are given
+				let ngram_ratio = match ngram_size_weights.get(ngram_size - 1) {
+					Some(w) => w / ngram_size_weights_total,
+					None => 1.0 / dataset.ngram_frequencies.len() as f64,
+				};
+				if ngram_ratio == 0.0 {
+					continue;
+				}
+				let (calculated_score, calculated_positions) = self.score_single_grams(layout, &dataset.ngram_frequencies[ngram_size], save_positions)?;
+				dataset_score += calculated_score * ngram_ratio;
 				if save_positions {
 					visited_positions.extend(calculated_positions);
 				}
@@ -178,26 +216,11 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 	}
 
 
-	fn score_population(&self, layouts: Vec<Layout<R, C>>, datasets: &[FrequencyDataset<u32>]) -> Result<Vec<(Layout<R, C>, f64)>, AlcError> {
+	fn score_population<T>(&self, layouts: Vec<Layout<R, C>>, datasets: &[FrequencyDataset<T>]) -> Result<Vec<(Layout<R, C>, f64)>, AlcError> where T: Frequencies {
 
 		// println!("num threads {}", self.config.num_threads);
 		let pool = rayon::ThreadPoolBuilder::new().num_threads(self.config.num_threads).build().unwrap();
-		let mut scores: Vec<Result<f64, AlcError>> = Default::default();
-		pool.install(|| {
-			layouts.par_iter()
-				.map(|x| {
-					let computed_scores = self.score_datasets(x, datasets, false);
-					match computed_scores {
-						Ok(v) => Ok(v.0),
-						Err(e) => return Err(e),
-					}
-				}).collect_into_vec(&mut scores)
-		});
-		let mut scores2: Vec<f64> = Default::default();
-		for score in scores {
-			scores2.push(score?);
-		}
-		Ok(zip(layouts, scores2).collect())
+		pool.install(|| self.score_layouts(layouts, datasets))
 		// let mut new_population: Vec<(Layout<R, C>, f64)> = Default::default();
 		// for layout in layouts {
 		// 	let (score, _) = self.score_datasets(layout, datasets, false);
@@ -205,127 +228,202 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		// }
 		// new_population
 	}
+
+	/// Scores on whichever thread pool is current, so islands can share the pool created in [Self::evolve_islands]
+	fn score_layouts<T>(&self, layouts: Vec<Layout<R, C>>, datasets: &[FrequencyDataset<T>]) -> Result<Vec<(Layout<R, C>, f64)>, AlcError> where T: Frequencies {
+		let mut scores: Vec<Result<f64, AlcError>> = Default::default();
+		layouts.par_iter()
+			.map(|x| {
+				let computed_scores = self.score_datasets(x, datasets, false);
+				match computed_scores {
+					Ok(v) => Ok(v.0),
+					Err(e) => return Err(e),
+				}
+			}).collect_into_vec(&mut scores);
+		let mut scores2: Vec<f64> = Default::default();
+		for score in scores {
+			scores2.push(score?);
+		}
+		Ok(zip(layouts, scores2).collect())
+	}
 	
 
-	fn generate_and_score_initial_population(&self, rng: &mut impl Rng, datasets: &[FrequencyDataset<u32>]) -> Result<Vec<(Layout<R, C>, f64)>, AlcError> {
+	fn generate_and_score_initial_population<T>(&self, rng: &mut impl Rng, datasets: &[FrequencyDataset<T>], population_size: u32) -> Result<Vec<(Layout<R, C>, f64)>, AlcError> where T: Frequencies {
 		let valid_keycodes = &self.config.valid_keycodes;
 		let mut initial_population: Vec<(Layout<R, C>, f64)> = Default::default();
-		for _i in 0..self.config.genetic_options.population_size {
+		for _i in 0..population_size {
 			let mut initial_layout = self.base_layout.clone();
 			initial_layout.randomize(rng, valid_keycodes)?;
+			check_constraints(&self.config.constraints, &initial_layout, &self.phalanx_layer)?;
 			let (initial_score, _) = self.score_datasets(&initial_layout, datasets, false)?;
 			initial_population.push((initial_layout, initial_score));
 		}
 		Ok(initial_population)
 	}
 
-	fn take_best_layouts(&self, mut population: Vec<(Layout<R, C>, f64)>) -> (Vec<Layout<R, C>>, Vec<f64>) {
+	fn take_best_layouts(&self, mut population: Vec<(Layout<R, C>, f64)>, population_size: u32) -> (Vec<Layout<R, C>>, Vec<f64>) {
     	population.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
 		// will always take at least one layout
-		let num_to_take = (self.config.genetic_options.fitness_cutoff * (self.config.genetic_options.population_size as f64)).ceil() as usize;
+		let num_to_take = (self.config.genetic_options.fitness_cutoff * (population_size as f64)).ceil() as usize;
 		let _ = population.split_off(num_to_take); // the returned value is the low score ones
 		let (left, right): (Vec<Layout<R, C>>, Vec<f64>) =  population.into_iter().unzip();
 		(left, right)
 	}
 
-	fn generate_new_layouts(&self, rng: &mut impl Rng, mut layouts: Vec<Layout<R, C>>) -> Result<Vec<Layout<R, C>>, AlcError> {
-		let population_size = self.config.genetic_options.population_size;
+	/// Applies one random swap or replace mutation to `layout` and updates the operation counter. A mutation that breaks one of the configured constraints is undone and counts as doing nothing.
+	fn mutate_layout(&self, rng: &mut impl Rng, layout: &mut Layout<R, C>) -> Result<(), AlcError> {
 		let swap_threshold = self.config.genetic_options.swap_weight / (self.config.genetic_options.swap_weight + self.config.genetic_options.replace_weight);
 		let valid_keycodes = &self.config.valid_keycodes;
+		let backup = if self.config.constraints.is_empty() {
+			None
+		} else {
+			Some(layout.clone())
+		};
+
+		let roll: f64 = rng.gen();
+		// println!("roll {} vs swap threshold {}", roll, swap_threshold);
+		let (mut swap_happened, mut replace_happened) = (false, false);
+		if roll <= swap_threshold {
+			if let Some((combo_index, p)) = layout.generate_random_valid_combo_swap(rng) {
+				swap_happened = layout.swap_combo(combo_index, p)?;
+			} else {
+				let (p1, p2) = layout.generate_random_valid_swap(rng)?;
+				// println!("swapping {} and {}", p1, p2);
+				swap_happened = layout.swap(p1, p2)?;
+			}
+		} else if let Some(rule_index) = layout.generate_random_valid_magic_replace(rng) {
+			let keycode = valid_keycodes.choose(rng).unwrap();
+			replace_happened = layout.replace_magic(rule_index, *keycode)?;
+		} else if let Some(p) = layout.generate_valid_replace_position(rng) {
+			let keycode = valid_keycodes.choose(rng).unwrap();
+			replace_happened = layout.replace(p, *keycode)?;
+		}
+		if let Some(backup_layout) = backup {
+			if (swap_happened || replace_happened) && check_constraints(&self.config.constraints, layout, &self.phalanx_layer).is_err() {
+				*layout = backup_layout;
+				(swap_happened, replace_happened) = (false, false);
+			}
+		}
+
+		let mut op_counter = self.operation_counter.ops.write().unwrap();
+		op_counter.3 += 1;
+		if swap_happened {
+			op_counter.0 += 1;
+		} else if replace_happened {
+			op_counter.1 += 1;
+		} else {
+			op_counter.2 += 1;
+		}
+		Ok(())
+	}
+
+	fn generate_new_layouts(&self, rng: &mut impl Rng, mut layouts: Vec<Layout<R, C>>, population_size: u32) -> Result<Vec<Layout<R, C>>, AlcError> {
 		// modify surviving layouts
 		
 		// if there is only one layout, modify it otherwise no change will happen
 		if population_size == 1 {
-			let layout = &mut layouts[0];
-			let mut op_counter = self.operation_counter.ops.write().unwrap();
-			op_counter.3 += 1;
-			// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2, op_counter.3 + 1));
-
-			let roll: f64 = rng.gen();
-			// println!("{}, {}", roll, swap_threshold);
-			if roll <= swap_threshold {
-				let (p1, p2) = layout.generate_random_valid_swap(rng)?;
-				// let (p1, p2) = match layout.generate_random_valid_swap(rng) {
-				// 	Some((x, y)) => (x, y),
-				// 	None => (LayoutPosition::new(0, 0, 0), LayoutPosition::new(0, 0, 0)), // swapping the same position doesn't change the layout
-				// };
-				// println!("swapping {} and {}", p1, p2);
-				let swap_happened = layout.swap(p1, p2)?;
-				// let op_counter = self.operation_counter.get();
-				if swap_happened {
-					op_counter.0 += 1;
-					// self.operation_counter.set((op_counter.0 + 1, op_counter.1, op_counter.2, op_counter.3));
-				} else {
-					op_counter.2 += 1;
-					// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-				}
-			} else if let Some(p) = layout.generate_valid_replace_position(rng) {
-				// println!("valid keycodes {:?}", valid_keycodes);
-				let keycode = valid_keycodes.choose(rng).unwrap();
-				let replace_happened = layout.replace(p, *keycode)?;
-				// let op_counter = self.operation_counter.get();
-				if replace_happened {
-					op_counter.1 += 1;
-					// self.operation_counter.set((op_counter.0, op_counter.1 + 1, op_counter.2, op_counter.3));
-				} else {
-					op_counter.2 += 1;
-					// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-				}
-			} else {
-				op_counter.2 += 1;
-				// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-			}
+			self.mutate_layout(rng, &mut layouts[0])?;
 		}
 		// fill up to population size
 		while layouts.len() < (population_size as usize) {
-			let mut op_counter = self.operation_counter.ops.write().unwrap();
-			op_counter.3 += 1;
-			// let op_counter = self.operation_counter.get();	
-			// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2, op_counter.3 + 1));
-
 			let mut new_layout = layouts.choose(rng).unwrap().clone();
-			let roll: f64 = rng.gen();
-			// println!("{}", roll);
-			// println!("roll {} vs swap threshold {}", roll, swap_threshold);
-			if roll <= swap_threshold {
-				let (p1, p2) = new_layout.generate_random_valid_swap(rng)?;
-				// let (p1, p2) = match  {
-				// 	Some((x, y)) => (x, y),
-				// 	None => return Err(AlcError::GenericError(String::from("no swap found, probably too many locked / symmetric positions"))),
-				// 	//(LayoutPosition::for_layout(0, 0, 0), LayoutPosition::for_layout(0, 0, 0)), // swapping the same position doesn't change the layout
-				// };
-				// println!("swapping {} and {}", p1, p2);
-				let swap_happened = new_layout.swap(p1, p2)?;
-				// let op_counter = self.operation_counter.get();
-				if swap_happened {
-					op_counter.0 += 1;
-					// self.operation_counter.set((op_counter.0 + 1, op_counter.1, op_counter.2, op_counter.3));
-				} else {
-					op_counter.2 += 1;
-					// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-				}
-			} else if let Some(p) = new_layout.generate_valid_replace_position(rng) {
-
-				let keycode = valid_keycodes.choose(rng).unwrap();
-				let replace_happened = new_layout.replace(p, *keycode)?;
-				// let op_counter = self.operation_counter.get();
-				if replace_happened {
-					op_counter.1 += 1;
-					// self.operation_counter.set((op_counter.0, op_counter.1 + 1, op_counter.2, op_counter.3));
-				} else {
-					op_counter.2 += 1
-					// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-				}
-			} else {
-				op_counter.2 += 1;
-				// self.operation_counter.set((op_counter.0, op_counter.1, op_counter.2 + 1, op_counter.3));
-			}
-			
+			self.mutate_layout(rng, &mut new_layout)?;
 			layouts.push(new_layout);
 		}
 		Ok(layouts)
 	}
 
+	/// Splits the population into `island_count` islands that evolve independently on the thread pool. If the population doesn't split evenly, the first islands get one extra layout each. Every `migration_interval` generations, each island's best layout replaces the worst layout of the next island (in a ring). Each island has its own rng seeded from `rng`, so results only depend on the seed and not on the number of threads. Returns every island's final population, unsorted, the initial population time, and the take best, generate, and score times summed over generations, where each generation counts the slowest island.
+	fn evolve_islands<T>(&self, rng: &mut impl Rng, datasets: &[FrequencyDataset<T>], current_step_file: &str) -> Result<(Population<R, C>, f64, GenerationTimes), AlcError> where T: Frequencies {
+		let genetic_options = self.config.genetic_options;
+		let island_count = genetic_options.island_count;
+		let island_sizes = (0..island_count).map(|i| {
+			genetic_options.population_size / island_count as u32 + u32::from(i < genetic_options.population_size as usize % island_count)
+		}).collect::<Vec<u32>>();
+
+		let now = SystemTime::now();
+		let mut islands: Vec<Island<R, C>> = Default::default();
+		for island_population_size in island_sizes.iter() {
+			let mut island_rng = ChaCha8Rng::seed_from_u64(rng.gen());
+			let initial_population = self.generate_and_score_initial_population(&mut island_rng, datasets, *island_population_size)?;
+			islands.push((island_rng, initial_population));
+		}
+		let initial_time = now.elapsed().unwrap().as_secs_f64();
+		write_text_to_file(current_step_file, String::from("Processed initial population"))?;
+
+		let (mut take_time, mut gen_time, mut score_time) = (0.0, 0.0, 0.0);
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(self.config.num_threads).build().unwrap();
+		for i in tqdm(0..genetic_options.generation_count) {
+			let mut evolved_islands: Vec<Result<(Island<R, C>, GenerationTimes), AlcError>> = Default::default();
+			pool.install(|| {
+				islands.into_par_iter().zip(island_sizes.par_iter())
+					.map(|((mut island_rng, population), island_population_size)| {
+						let mut now = SystemTime::now();
+						let (best_layouts, _) = self.take_best_layouts(population, *island_population_size);
+						let island_take_time = now.elapsed().unwrap().as_secs_f64();
+						now = SystemTime::now();
+						let layouts = self.generate_new_layouts(&mut island_rng, best_layouts, *island_population_size)?;
+						let island_gen_time = now.elapsed().unwrap().as_secs_f64();
+						now = SystemTime::now();
+						let layouts_and_scores = self.score_layouts(layouts, datasets)?;
+						let island_score_time = now.elapsed().unwrap().as_secs_f64();
+						Ok(((island_rng, layouts_and_scores), (island_take_time, island_gen_time, island_score_time)))
+					}).collect_into_vec(&mut evolved_islands)
+			});
+			let evolved_islands = evolved_islands.into_iter().collect::<Result<Vec<_>, AlcError>>()?;
+			let island_times = evolved_islands.iter().map(|(_, times)| *times).collect::<Vec<GenerationTimes>>();
+			take_time += island_times.iter().map(|t| t.0).fold(0.0, f64::max);
+			gen_time += island_times.iter().map(|t| t.1).fold(0.0, f64::max);
+			score_time += island_times.iter().map(|t| t.2).fold(0.0, f64::max);
+			islands = evolved_islands.into_iter().map(|(island, _)| island).collect();
+
+			if genetic_options.migration_interval > 0 && (i + 1) % genetic_options.migration_interval == 0 {
+				migrate(&mut islands);
+			}
+
+			let best_score = islands.iter().flat_map(|(_, population)| population.iter().map(|x| x.1)).fold(f64::INFINITY, f64::min);
+			println!("after {} generation(s), best score across {} islands: {}", i, island_count, best_score);
+			write_text_to_file(current_step_file, format!("Finished generation {} / {}", i, genetic_options.generation_count))?;
+		}
+		Ok((islands.into_iter().flat_map(|(_, population)| population).collect(), initial_time, (take_time, gen_time, score_time)))
+	}
+
+	/// Exhaustive 2-swap hill climbing over [Layout::valid_swaps]. Each pass applies the single swap that improves the score the most, until no swap improves it. Returns the polished layout, its score, and the swaps in the order they were applied.
+	pub fn polish_layout<T>(&self, layout: Layout<R, C>, score: f64, datasets: &[FrequencyDataset<T>]) -> Result<(Layout<R, C>, f64, PositionSwaps), AlcError> where T: Frequencies {
+		let pool = rayon::ThreadPoolBuilder::new().num_threads(self.config.num_threads).build().unwrap();
+		let mut current_layout = layout;
+		let mut current_score = score;
+		let mut applied_swaps: PositionSwaps = vec![];
+		loop {
+			let swaps = current_layout.valid_swaps();
+			let mut swap_scores: Vec<Result<f64, AlcError>> = Default::default();
+			pool.install(|| {
+				swaps.par_iter()
+					.map(|(p1, p2)| {
+						let mut candidate = current_layout.clone();
+						if candidate.swap(*p1, *p2)? && check_constraints(&self.config.constraints, &candidate, &self.phalanx_layer).is_ok() {
+							Ok(self.score_datasets(&candidate, datasets, false)?.0)
+						} else {
+							Ok(f64::INFINITY)
+						}
+					}).collect_into_vec(&mut swap_scores)
+			});
+			let swap_scores = swap_scores.into_iter().collect::<Result<Vec<f64>, AlcError>>()?;
+			if swap_scores.is_empty() {
+				break;
+			}
+			let best_index = arg_min(&swap_scores)?;
+			if swap_scores[best_index] >= current_score {
+				break;
+			}
+			let (p1, p2) = swaps[best_index];
+			current_layout.swap(p1, p2)?;
+			current_score = swap_scores[best_index];
+			applied_swaps.push((p1, p2));
+		}
+		Ok((current_layout, current_score, applied_swaps))
+	}
+
 	pub fn optimize(&mut self, rng: &mut impl Rng, base_filename: Option<String>) -> Result<Layout<R, C>, AlcError> {
 		
 		let mut cache_dir = dirs::cache_dir().unwrap().into_os_string();
@@ -340,33 +438,34 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		if datasets.is_empty() {
 			return Err(AlcError::GenericError(String::from("no datasets to optimize")));
 		}
-		if datasets.len() != self.config.dataset_options.dataset_weights.len() {
-			return Err(AlcError::DatasetWeightsMismatchError(datasets.len(), self.config.dataset_options.dataset_weights.len()));
-		}
+		self.check_dataset_weights(datasets)?;
 		write_text_to_file(&current_step_file, String::from("Loaded datasets"))?;
 
 		self.activate();
 		println!("base layout\n{}", self.base_layout);
-		for dataset in datasets {
-			let onegram = dataset.get(&4).unwrap().clone();
-			let mut onegram_sorted = onegram.iter().collect::<Vec<(&Ngram, &u32)>>();
-			onegram_sorted.sort_by(|a, b| b.1.cmp(a.1));
-			println!("fourgrams");
-			for (gram, count) in onegram_sorted {
-				println!("{}: {}", gram, count);
-			}
-		}
-
+		check_constraints(&self.config.constraints, &self.base_layout, &self.phalanx_layer)?;
 		let mut avg_score_time = 0.0;
 		let mut avg_take_time = 0.0;
 		let mut avg_gen_time = 0.0;
 
 		let mut now = SystemTime::now();
-		let mut layouts_and_scores = self.generate_and_score_initial_population(rng, datasets)?;
-		
-		write_text_to_file(&current_step_file, String::from("Processed initial population"))?;
+		let population_size = self.config.genetic_options.population_size;
+		let island_count = self.config.genetic_options.island_count;
+		if island_count == 0 || island_count > population_size as usize {
+			return Err(AlcError::IslandCountError(island_count, population_size));
+		}
+		if !(population_size as usize).is_multiple_of(island_count) {
+			println!("population_size {} doesn't split evenly into {} islands, the first {} island(s) get one extra layout", population_size, island_count, population_size as usize % island_count);
+		}
+		let mut layouts_and_scores = if island_count == 1 {
+			let initial_population = self.generate_and_score_initial_population(rng, datasets, population_size)?;
+			write_text_to_file(&current_step_file, String::from("Processed initial population"))?;
+			initial_population
+		} else {
+			Default::default()
+		};
 
-		let initial_time = now.elapsed().unwrap().as_secs_f64();
+		let mut initial_time = now.elapsed().unwrap().as_secs_f64();
 			
 		let mut layouts: Vec<Layout<R, C>>; // = Default::default();
 		let mut best_layouts: Vec<Layout<R, C>>;
@@ -374,25 +473,39 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 
 		// let tcount = 20;
 		
-		for i in tqdm(0..self.config.genetic_options.generation_count) {
+		if island_count > 1 {
+			(layouts_and_scores, initial_time, (avg_take_time, avg_gen_time, avg_score_time)) = self.evolve_islands(rng, datasets, &current_step_file)?;
+		} else {
+			for i in tqdm(0..self.config.genetic_options.generation_count) {
 
-			now = SystemTime::now();
-			(best_layouts, best_scores) = self.take_best_layouts(layouts_and_scores);
-			avg_take_time +=  now.elapsed().unwrap().as_secs_f64();
+				now = SystemTime::now();
+				(best_layouts, best_scores) = self.take_best_layouts(layouts_and_scores, population_size);
+				avg_take_time +=  now.elapsed().unwrap().as_secs_f64();
 
-			now = SystemTime::now();
-			layouts = self.generate_new_layouts(rng, best_layouts)?;
-			avg_gen_time +=  now.elapsed().unwrap().as_secs_f64();
+				now = SystemTime::now();
+				layouts = self.generate_new_layouts(rng, best_layouts, population_size)?;
+				avg_gen_time +=  now.elapsed().unwrap().as_secs_f64();
 
-			now = SystemTime::now();
-			layouts_and_scores = self.score_population(layouts, datasets)?;
-			avg_score_time += now.elapsed().unwrap().as_secs_f64();
-			
-			println!("after {} generation(s), best score: {}, worst score {}", i, best_scores[0], best_scores[best_scores.len()-1]);
-			
-			write_text_to_file(&current_step_file, format!("Finished generation {} / {}", i, self.config.genetic_options.generation_count))?;
+				now = SystemTime::now();
+				layouts_and_scores = self.score_population(layouts, datasets)?;
+				avg_score_time += now.elapsed().unwrap().as_secs_f64();
+				
+				println!("after {} generation(s), best score: {}, worst score {}", i, best_scores[0], best_scores[best_scores.len()-1]);
+				
+				write_text_to_file(&current_step_file, format!("Finished generation {} / {}", i, self.config.genetic_options.generation_count))?;
+			}
+		}
+		(best_layouts, best_scores) = self.take_best_layouts(layouts_and_scores, population_size);
+		if self.config.genetic_options.polish_final_layout {
+			println!("polishing best layout, score before: {}", best_scores[0]);
+			let (polished_layout, polished_score, applied_swaps) = self.polish_layout(best_layouts[0].clone(), best_scores[0], datasets)?;
+			for (p1, p2) in applied_swaps.iter() {
+				println!("    swapped {} and {}", p1, p2);
+			}
+			println!("score after polishing: {} ({} swap(s) applied)", polished_score, applied_swaps.len());
+			best_layouts[0] = polished_layout;
+			best_scores[0] = polished_score;
 		}
-		(best_layouts, best_scores) = self.take_best_layouts(layouts_and_scores);
 		// let mut final_layout = best_layouts[0].clone();
 		// println!("final layout pre removal\n{}score: {}", final_layout, best_scores[0]);
 
@@ -495,9 +608,7 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		if datasets.is_empty() {
 			return Err(AlcError::GenericError(String::from("no datasets to optimize")));
 		}
-		if datasets.len() != self.config.dataset_options.dataset_weights.len() {
-			return Err(AlcError::DatasetWeightsMismatchError(datasets.len(), self.config.dataset_options.dataset_weights.len()));
-		}
+		self.check_dataset_weights(datasets)?;
 		Ok(self.score_datasets(&self.base_layout, datasets, false)?.0)
 	}
 
@@ -506,7 +617,11 @@ impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<
 		// let num_cols = t.layout_info.num_cols;
 		// let panic_message = format!("{} x {} layout preset does not exist yet, choose the next largest layout and block key positions. List of available layout sizes should go here: ", R, C);
 		
-		let (base_layout, effort_layer, phalanx_layer) = (Layout::<R, C>::try_from(t.layout_info.layout.as_str())?, Layer::<R, C, f64>::try_from(t.layout_info.effort_layer.as_str())?, Layer::<R, C, PhalanxKey>::try_from(t.layout_info.phalanx_layer.as_str())?);
+		let (mut base_layout, effort_layer, phalanx_layer) = (Layout::<R, C>::try_from(t.layout_info.layout.as_str())?, Layer::<R, C, f64>::try_from(t.layout_info.effort_layer.as_str())?, Layer::<R, C, PhalanxKey>::try_from(t.layout_info.phalanx_layer.as_str())?);
+		let combos = t.layout_info.combos.iter().map(|c| c.try_to_combo()).collect::<Result<Vec<_>, AlcError>>()?;
+		base_layout.set_combos(combos)?;
+		let magic_rules = t.layout_info.magic.iter().map(|m| m.try_to_magic_rule()).collect::<Result<Vec<_>, AlcError>>()?;
+		base_layout.set_magic_rules(magic_rules)?;
 
 		Ok(Self {
 			base_layout,
@@ -593,6 +708,21 @@ pub fn optimize_from_toml(filename: String) -> Result<String, AlcError> {
 }
 
 
+/// Ring migration: a copy of island i's best layout replaces the worst layout of island i + 1
+fn migrate<const R: usize, const C: usize, T>(islands: &mut [(T, Vec<(Layout<R, C>, f64)>)]) {
+	let island_count = islands.len();
+	let migrants: Vec<Option<(Layout<R, C>, f64)>> = islands.iter().map(|(_, population)| {
+		population.iter().min_by(|a, b| a.1.total_cmp(&b.1)).cloned()
+	}).collect();
+	for (i, migrant) in migrants.into_iter().enumerate() {
+		let (_, destination) = &mut islands[(i + 1) % island_count];
+		let worst_index = destination.iter().enumerate().max_by(|(_, a), (_, b)| a.1.total_cmp(&b.1)).map(|(idx, _)| idx);
+		if let (Some(migrant), Some(worst_index)) = (migrant, worst_index) {
+			destination[worst_index] = migrant;
+		}
+	}
+}
+
 fn arg_min(scores: &[f64]) -> Result<usize, AlcError> {
 	let min_index = match scores.iter().enumerate().min_by(|(_, a), (_, b)| a.total_cmp(b)).map(|(idx, _)| idx) {
 		Some(v) => v,
@@ -659,7 +789,10 @@ pub fn score_from_toml(filename: String) -> Result<f64, AlcError> {
 mod tests {
 
 	use super::*;
+	use std::collections::HashMap;
 	use rand_chacha::ChaCha8Rng;
+	use crate::keyboard::key::KeyValue;
+	use crate::text_processor::keycode::{Keycode::*, KeycodeOptions};
 
 	#[test]
 	fn test_arg_min () -> Result<(), AlcError> {
@@ -692,7 +825,7 @@ mod tests {
 		let datasets = layout_optimizer.compute_datasets()?;
 		let twogram_frequency = datasets[0].ngram_frequencies.get(&(2 as usize)).unwrap();
 		println!("{:?}", twogram_frequency);
-		let (s, poss) = layout_optimizer.score_single_grams(&test_layout, twogram_frequency.clone(), true)?;
+		let (s, poss) = layout_optimizer.score_single_grams(&test_layout, twogram_frequency, true)?;
 		// 3 * he + 1 * be + 2 * eh + 1 + eb
 		let mut expected_two_score = (3.0 * (0.1 + 0.2 + 0.1) + 1.0 * (0.3 + 0.2 + 0.1) + 2.0 * (0.2 + 0.1 + 0.1) + 1.0 * (0.2 + 0.1 + 0.3)) / 7.0;
 		expected_two_score *= 1.1;
@@ -712,6 +845,170 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_islands_deterministic() -> Result<(), AlcError> {
+		let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		ngram_frequencies.insert(1, SingleGramFrequencies::try_from_string("hellothereotherhole", 1, &KeycodeOptions::default())?.unwrap());
+		ngram_frequencies.insert(2, SingleGramFrequencies::try_from_string("hellothereotherhole", 2, &KeycodeOptions::default())?.unwrap());
+		let datasets = vec![FrequencyDataset { name: String::from("test"), ngram_frequencies }];
+
+		let mut final_populations: Vec<Vec<(Layout<2, 4>, f64)>> = vec![];
+		for num_threads in [1, 4] {
+			let mut lo = LayoutOptimizer::<2, 4, AdvancedScoreFunction>::default();
+			lo.config.genetic_options.population_size = 13;
+			lo.config.genetic_options.generation_count = 5;
+			lo.config.genetic_options.fitness_cutoff = 0.5;
+			lo.config.genetic_options.island_count = 3;
+			lo.config.genetic_options.migration_interval = 2;
+			lo.config.num_threads = num_threads;
+			lo.config.valid_keycodes = vec![_H, _E, _L, _O, _T, _R];
+			let mut rng = ChaCha8Rng::seed_from_u64(1);
+			let current_step_file = std::env::temp_dir().join(format!("alc_test_islands_{}.txt", std::process::id()));
+			let (population, _, _) = lo.evolve_islands(&mut rng, &datasets, current_step_file.to_str().unwrap())?;
+			fs::remove_file(current_step_file).unwrap();
+			assert_eq!(population.len(), 13);
+			final_populations.push(population);
+		}
+		assert_eq!(final_populations[0], final_populations[1]);
+		Ok(())
+	}
+
+	#[test]
+	fn test_ngram_size_weights() -> Result<(), AlcError> {
+		let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		ngram_frequencies.insert(1, SingleGramFrequencies::try_from_string("hellothere", 1, &KeycodeOptions::default())?.unwrap());
+		ngram_frequencies.insert(2, SingleGramFrequencies::try_from_string("hellothere", 2, &KeycodeOptions::default())?.unwrap());
+		let datasets = vec![FrequencyDataset { name: String::from("test"), ngram_frequencies }];
+		let layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			H_10 E_10 L_10 O_10
+			T_10 R_10 __10 __10
+		")?;
+
+		let mut lo = LayoutOptimizer::<2, 4, AdvancedScoreFunction>::default();
+		lo.config.dataset_options.max_ngram_size = 2;
+		let (one_gram_score, _) = lo.score_single_grams(&layout, &datasets[0].ngram_frequencies[&1], false)?;
+		let (two_gram_score, _) = lo.score_single_grams(&layout, &datasets[0].ngram_frequencies[&2], false)?;
+		assert_eq!(lo.score_datasets(&layout, &datasets, false)?.0, (one_gram_score + two_gram_score) / 2.0);
+		lo.config.dataset_options.ngram_size_weights = vec![1.0, 3.0];
+		assert_eq!(lo.score_datasets(&layout, &datasets, false)?.0, 0.25 * one_gram_score + 0.75 * two_gram_score);
+		lo.config.dataset_options.ngram_size_weights = vec![0.0, 1.0];
+		assert_eq!(lo.score_datasets(&layout, &datasets, false)?.0, two_gram_score);
+
+		assert!(lo.check_dataset_weights(&datasets).is_ok());
+		lo.config.dataset_options.ngram_size_weights = vec![1.0, 1.0, 1.0];
+		assert!(matches!(lo.check_dataset_weights(&datasets), Err(AlcError::NgramSizeWeightsMismatchError(3, 2))));
+		lo.config.dataset_options.ngram_size_weights = vec![1.0, -1.0];
+		assert_eq!(lo.check_dataset_weights(&datasets), Err(AlcError::NegativeNgramSizeWeightError(-1.0)));
+		lo.config.dataset_options.ngram_size_weights = vec![0.0, 0.0];
+		assert_eq!(lo.check_dataset_weights(&datasets), Err(AlcError::ZeroNgramSizeWeightsError));
+
+		// a dataset without 2-grams, e.g., a table of letter frequencies, still counts fully next to one that has them
+		let mut onegram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		onegram_frequencies.insert(1, datasets[0].ngram_frequencies[&1].clone());
+		let datasets = vec![datasets[0].clone(), FrequencyDataset { name: String::from("onegrams"), ngram_frequencies: onegram_frequencies }];
+		lo.config.dataset_options.dataset_weights = vec![1.0, 1.0];
+		lo.config.dataset_options.ngram_size_weights = vec![1.0, 3.0];
+		assert!(lo.check_dataset_weights(&datasets).is_ok());
+		let expected_score = 0.5 * (0.25 * one_gram_score + 0.75 * two_gram_score) + 0.5 * one_gram_score;
+		assert!((lo.score_datasets(&layout, &datasets, false)?.0 - expected_score).abs() < 1e-12);
+		lo.config.dataset_options.ngram_size_weights = vec![0.0, 1.0];
+		assert_eq!(lo.check_dataset_weights(&datasets), Err(AlcError::DatasetZeroNgramSizeWeightsError(String::from("onegrams"))));
+		Ok(())
+	}
+
+	#[test]
+	fn test_blended_dataset_scoring() -> Result<(), AlcError> {
+		let mut datasets = vec![];
+		for text in ["hellothere", "otherhole", "teeth"] {
+			let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+			ngram_frequencies.insert(1, SingleGramFrequencies::try_from_string(text, 1, &KeycodeOptions::default())?.unwrap());
+			ngram_frequencies.insert(2, SingleGramFrequencies::try_from_string(text, 2, &KeycodeOptions::default())?.unwrap());
+			datasets.push(FrequencyDataset { name: String::from(text), ngram_frequencies });
+		}
+		let layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			H_10 E_10 L_10 O_10
+			T_10 R_10 __10 __10
+		")?;
+
+		let mut lo = LayoutOptimizer::<2, 4, AdvancedScoreFunction>::default();
+		lo.config.dataset_options.dataset_weights = vec![1.0, 0.5, 2.0];
+		let (separate_score, _) = lo.score_datasets(&layout, &datasets, false)?;
+		let blended = FrequencyDataset::blend(String::from("blended"), &[(&datasets[0], 1.0), (&datasets[1], 0.5), (&datasets[2], 2.0)])?;
+		lo.config.dataset_options.dataset_weights = vec![1.0];
+		let (blended_score, _) = lo.score_datasets(&layout, &[blended], false)?;
+		assert!((separate_score - blended_score).abs() < 1e-12);
+		let (normalized_score, _) = lo.score_datasets(&layout, &[datasets[0].normalized()], false)?;
+		let (count_score, _) = lo.score_datasets(&layout, &datasets[..1], false)?;
+		assert!((normalized_score - count_score).abs() < 1e-12);
+		Ok(())
+	}
+
+	#[test]
+	fn test_migrate() {
+		let layout_a = Layout::<1, 4>::try_from("
+			___Layer 0___
+			A_10 B_10 C_10 D_10
+		").unwrap();
+		let layout_b = Layout::<1, 4>::try_from("
+			___Layer 0___
+			B_10 A_10 C_10 D_10
+		").unwrap();
+		let mut islands = vec![
+			((), vec![(layout_a.clone(), 1.0), (layout_a.clone(), 3.0)]),
+			((), vec![(layout_b.clone(), 2.0), (layout_b.clone(), 4.0)]),
+		];
+		migrate(&mut islands);
+		// island 0's best replaces island 1's worst and vice versa
+		assert_eq!(islands[1].1[1], (layout_a, 1.0));
+		assert_eq!(islands[0].1[1], (layout_b, 2.0));
+	}
+
+	#[test]
+	fn test_polish_layout() -> Result<(), AlcError> {
+		let base_layout = Layout::<1, 4>::init_blank(1);
+		let effort_layer = Layer::<1, 4, f64>::try_from("
+			0.1 0.2 0.3 0.4
+		").unwrap();
+		let phalanx_layer = Layer::<1, 4, PhalanxKey>::try_from("
+			l:m l:i r:i r:m
+		").unwrap();
+		let layout = Layout::<1, 4>::try_from("
+			___Layer 0___
+			H_10 E_10 B_10 A_00
+		").unwrap();
+		let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		ngram_frequencies.insert(1, SingleGramFrequencies::try_from_string("bbbbbbeeeh", 1, &KeycodeOptions::default())?.unwrap());
+		let datasets = vec![FrequencyDataset { name: String::from("test"), ngram_frequencies }];
+		let layout_optimizer = LayoutOptimizer::new(base_layout, effort_layer, phalanx_layer, SimpleScoreFunction{}, LayoutOptimizerConfig::default(), OperationCounter::new((0, 0, 0, 0)));
+
+		let (score, _) = layout_optimizer.score_datasets(&layout, &datasets, false)?;
+		let (polished, polished_score, swaps) = layout_optimizer.polish_layout(layout.clone(), score, &datasets)?;
+		assert_eq!(swaps, vec![(LayoutPosition::new(0, 0, 0), LayoutPosition::new(0, 0, 2))]);
+		assert_eq!(polished[(0, 0, 0)].value(), _B);
+		assert_eq!(polished[(0, 0, 1)].value(), _E);
+		assert_eq!(polished[(0, 0, 2)].value(), _H);
+		assert_eq!(polished[(0, 0, 3)].value(), _A);
+		assert_eq!(format!("{polished_score:.3}"), format!("{:.3}", (6.0 * 0.1 + 3.0 * 0.2 + 1.0 * 0.3) / 10.0));
+
+		// the same frequencies as probabilities from an n-gram table
+		let table_filename = std::env::temp_dir().join(format!("alc_test_polish_layout_{}.csv", std::process::id()));
+		fs::write(&table_filename, "ngram,probability\nb,0.6\ne,0.3\nh,0.1\n").unwrap();
+		let mut config = LayoutOptimizerConfig::default();
+		config.dataset_options.max_ngram_size = 1;
+		config.dataset_options.dataset_paths = vec![table_filename.to_string_lossy().to_string()];
+		let layout_optimizer = LayoutOptimizer::new(Layout::<1, 4>::init_blank(1), layout_optimizer.effort_layer.clone(), layout_optimizer.phalanx_layer.clone(), SimpleScoreFunction{}, config, OperationCounter::new((0, 0, 0, 0)));
+		let probabilities = layout_optimizer.compute_datasets()?;
+		fs::remove_file(table_filename).unwrap();
+		let (table_score, _) = layout_optimizer.score_datasets(&layout, &probabilities, false)?;
+		assert!((table_score - score).abs() < 1e-12);
+		let (table_polished, table_polished_score, table_swaps) = layout_optimizer.polish_layout(layout, table_score, &probabilities)?;
+		assert_eq!((table_polished, table_swaps), (polished, swaps));
+		assert!((table_polished_score - polished_score).abs() < 1e-12);
+		Ok(())
+	}
+
 	// #[test]
 	// fn test_saved() -> Result<(), AlcError> {
 	// 	let mut _lo = LayoutOptimizer::<4, 10, AdvancedScoreFunction>::try_from_optimizer_toml_file("/home/sand/.config/alc/autosave.toml")?;
@@ -4,15 +4,22 @@ use serde_derive::{Deserialize, Serialize};
 use toml;
 
 use crate::alc_error::AlcError;
+use crate::keyboard::{combo::Combo, key::KeycodeKey, magic::MagicRule, LayoutPosition};
+use crate::text_processor::{dataset::ExtractionOptions, frequency_holder::{LineEndings, TopFrequenciesToTake}, preprocessing::PreprocessingOptions};
 use super::{keycode::{Keycode, KeycodeOptions}, LayoutOptimizer, Score};
+use super::constraint::KeycodeConstraint;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Copy, Clone)]
+#[serde(default)]
 pub struct GeneticOptions {
 	pub population_size: u32,
 	pub generation_count: u32,
 	pub fitness_cutoff: f64, // keep top x% for the next generation
 	pub swap_weight: f64,
 	pub replace_weight: f64,
+	pub island_count: usize, // population_size is split between islands, with the first islands getting the remainder
+	pub migration_interval: u32, // 0 means islands never exchange layouts
+	pub polish_final_layout: bool,
 }
 impl Default for GeneticOptions {
 	fn default() -> Self {
@@ -22,6 +29,9 @@ impl Default for GeneticOptions {
 			fitness_cutoff: 0.1,
 			swap_weight: 4.0,
 			replace_weight: 1.0,
+			island_count: 1,
+			migration_interval: 10,
+			polish_final_layout: false,
 		}
 	}
 }
@@ -32,19 +42,93 @@ pub struct DatasetOptions {
 	pub dataset_weights: Vec<f64>,
 	pub max_ngram_size: usize,
 	pub top_n_ngrams_to_take: usize,
+	#[serde(default)]
+	pub ngram_coverage: Vec<f64>,
+	#[serde(default)]
+	pub ngram_size_weights: Vec<f64>,
+	#[serde(default)]
+	pub include_globs: Vec<String>,
+	#[serde(default)]
+	pub exclude_globs: Vec<String>,
+	#[serde(default = "default_max_ngrams_in_memory")]
+	pub max_ngrams_in_memory: usize,
+	#[serde(default)]
+	pub line_endings: LineEndings,
+	#[serde(default)]
+	pub intra_word_only: bool,
+	#[serde(default)]
+	pub strip_indentation: bool,
+	#[serde(default)]
+	pub drop_auto_paired: bool,
+	#[serde(default)]
+	pub max_identifier_length: usize,
+	#[serde(default)]
+	pub skip_comments: bool,
+	#[serde(default)]
+	pub skip_string_contents: bool,
 }
 impl Default for DatasetOptions {
 	fn default() -> Self {
 		DatasetOptions {
 			dataset_weights: vec![1.0],
-			dataset_paths: vec![String::from("./data/rust_book_test/")],
+			dataset_paths: vec![String::from("preset:english")],
 			max_ngram_size: 4,
 			top_n_ngrams_to_take: 100,
+			ngram_coverage: vec![],
+			ngram_size_weights: vec![],
+			include_globs: vec![],
+			exclude_globs: vec![],
+			max_ngrams_in_memory: 1_000_000,
+			line_endings: LineEndings::default(),
+			intra_word_only: false,
+			strip_indentation: false,
+			drop_auto_paired: false,
+			max_identifier_length: 0,
+			skip_comments: false,
+			skip_string_contents: false,
+		}
+	}
+}
+fn default_max_ngrams_in_memory() -> usize {
+	1_000_000
+}
+impl DatasetOptions {
+	/// `ngram_coverage` if given, either one fraction for every n-gram size or one per size, otherwise `top_n_ngrams_to_take` for every size
+	pub fn top_frequencies_to_take(&self) -> Result<Vec<TopFrequenciesToTake>, AlcError> {
+		if self.ngram_coverage.is_empty() {
+			return Ok(vec![TopFrequenciesToTake::Num(self.top_n_ngrams_to_take)]);
+		}
+		if self.ngram_coverage.len() != 1 && self.ngram_coverage.len() != self.max_ngram_size {
+			return Err(AlcError::NgramCoverageMismatchError(self.ngram_coverage.len(), self.max_ngram_size));
+		}
+		self.ngram_coverage.iter().map(|c| {
+			if *c > 0.0 && *c <= 1.0 {
+				Ok(TopFrequenciesToTake::Coverage(*c))
+			} else {
+				Err(AlcError::NgramCoverageError(*c))
+			}
+		}).collect()
+	}
+	pub fn extraction_options(&self) -> ExtractionOptions {
+		ExtractionOptions {
+			include_globs: self.include_globs.clone(),
+			exclude_globs: self.exclude_globs.clone(),
+			max_ngrams_in_memory: self.max_ngrams_in_memory,
+			line_endings: self.line_endings,
+			intra_word_only: self.intra_word_only,
+			preprocessing: PreprocessingOptions {
+				strip_indentation: self.strip_indentation,
+				drop_auto_paired: self.drop_auto_paired,
+				max_identifier_length: self.max_identifier_length,
+				skip_comments: self.skip_comments,
+				skip_string_contents: self.skip_string_contents,
+			},
 		}
 	}
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct ScoreOptions {
 	pub hand_alternation_weight: f64, // determines the relative weight of hand alternation bonus vs finger roll bonus. 
 	pub finger_roll_weight: f64,
@@ -53,6 +137,8 @@ pub struct ScoreOptions {
 	pub finger_roll_same_row_reduction_factor: f64,
 	pub same_finger_penalty_factor: f64,
 	pub extra_length_penalty_factor: f64,
+	pub mod_tap_misfire_penalty_factor: f64, // same-hand rolls over mod-tap keys risk the tap being read as the hold or vice versa
+	pub combo_penalty_factor: f64,
 }
 impl Default for ScoreOptions {
 	fn default() -> Self {
@@ -64,6 +150,8 @@ impl Default for ScoreOptions {
 			finger_roll_same_row_reduction_factor: 0.9,
 			same_finger_penalty_factor: 5.0,
 			extra_length_penalty_factor: 1.1,
+			mod_tap_misfire_penalty_factor: 1.5,
+			combo_penalty_factor: 1.5,
 		}
 	}
 }
@@ -78,7 +166,8 @@ pub struct LayoutOptimizerConfig {
 	pub dataset_options: DatasetOptions,
 	pub score_options: ScoreOptions,
 	pub num_threads: usize,
-
+	#[serde(default)]
+	pub constraints: Vec<KeycodeConstraint>,
 }
 impl Default for LayoutOptimizerConfig {
 	fn default() -> Self {
@@ -92,6 +181,7 @@ impl Default for LayoutOptimizerConfig {
 			dataset_options: DatasetOptions::default(),
 			score_options: ScoreOptions::default(),
 			num_threads: 1,
+			constraints: vec![],
 		 }
 	}
 }
@@ -106,6 +196,47 @@ pub struct LayoutInfoTomlAdapter {
 	pub layout: String,
 	pub effort_layer: String,
 	pub phalanx_layer: String,
+	#[serde(default)]
+	pub combos: Vec<ComboTomlAdapter>,
+	#[serde(default)]
+	pub magic: Vec<MagicRuleTomlAdapter>,
+}
+
+/// Positions are (layer, row, col) tuples and the key is in the same format as keys in the layout string
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ComboTomlAdapter {
+	pub positions: Vec<(usize, usize, usize)>,
+	pub key: String,
+}
+impl ComboTomlAdapter {
+	pub fn try_to_combo(&self) -> Result<Combo, AlcError> {
+		let positions = self.positions.iter().map(|t| LayoutPosition::from_tuple(*t)).collect();
+		Combo::try_new(positions, KeycodeKey::try_from(self.key.as_str())?)
+	}
+	pub fn from_combo(combo: &Combo) -> Self {
+		ComboTomlAdapter {
+			positions: combo.positions().iter().map(|lp| (lp.layer_index, lp.row_index, lp.col_index)).collect(),
+			key: format!("{:b}", combo.key()).trim().to_string(),
+		}
+	}
+}
+
+/// The key is in the same format as keys in the layout string
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MagicRuleTomlAdapter {
+	pub previous: Keycode,
+	pub key: String,
+}
+impl MagicRuleTomlAdapter {
+	pub fn try_to_magic_rule(&self) -> Result<MagicRule, AlcError> {
+		MagicRule::try_new(self.previous, KeycodeKey::try_from(self.key.as_str())?)
+	}
+	pub fn from_magic_rule(rule: &MagicRule) -> Self {
+		MagicRuleTomlAdapter {
+			previous: rule.previous(),
+			key: format!("{:b}", rule.key()).trim().to_string(),
+		}
+	}
 }
 
 
@@ -205,6 +336,8 @@ impl LayoutOptimizerTomlAdapter {
 			layout: base_layout_string,
 			effort_layer: effort_layer_string,
 			phalanx_layer: phalanx_layer_string,
+			combos: lo.base_layout.combos().iter().map(ComboTomlAdapter::from_combo).collect(),
+			magic: lo.base_layout.magic_rules().iter().map(MagicRuleTomlAdapter::from_magic_rule).collect(),
 		};
 
 		LayoutOptimizerTomlAdapter {
@@ -237,14 +370,35 @@ pub fn option_descriptions() -> HashMap<String, String> {
 	options_map.insert(String::from("fitness_cutoff"), String::from("Fraction of best layouts per generation to duplicate and modify into layouts of the next generation. With a value of 1.0, all layouts will be retained generation to generation so no new ones will be created. With a value of 0.0, a single layout (the minimum possible) will be retained generation to generation; all layouts within a generation will be based on the best layout of the previous generation."));
 	options_map.insert(String::from("swap_weight"), String::from("swap_weight:replace_weight represents the ratio of swap mutations (i.e., swapping two keys) to replace mutations (i.e., replacing one key with another)."));
 	options_map.insert(String::from("replace_weight"), String::from("See swap_weight."));
+	options_map.insert(String::from("island_count"), String::from("Number of islands (sub-populations) that evolve independently of each other. The population is split evenly between islands, with the first islands getting one extra layout each if it does not divide evenly, so population_size should be at least island_count. With a value of 1, there is a single population. More islands explore more distinct layouts at the cost of each island being smaller. Results do not depend on num_threads."));
+	options_map.insert(String::from("migration_interval"), String::from("Every migration_interval generations, each island sends a copy of its best layout to the next island, replacing that island's worst layout. A value of 0 means islands never exchange layouts. Has no effect when island_count is 1."));
+	options_map.insert(String::from("polish_final_layout"), String::from("Whether to run a local search on the best layout after the last generation. Every valid swap of two keys is tried and the one that improves the score the most is applied, repeating until no single swap improves the score. The applied swaps and the scores before and after are printed. Can be slow for large layouts."));
 	options_map.insert(String::from("include_alphas"), String::from("Convenience toggle. Recommended to be set to true, as otherwise the user must manually place every alpha."));
-	options_map.insert(String::from("include_numbers"), String::from("Whether to include number keycodes. Recommended to set this to false with manual number placement -- optimized layouts cannot currently guarantee numbers to be arranged in order."));
+	options_map.insert(String::from("include_numbers"), String::from("Whether to include number keycodes. Recommended to set this to false with manual number placement, or to place the numbers in the initial layout and keep them in order with an InOrder entry in `constraints`."));
 	options_map.insert(String::from("include_number_symbols"), String::from("Whether shifted numbers (!@#$ etc.) should be considered their own keycodes. If false, these symbols must be accessed through shift+numbers. Recommended to set to false, as it is uncommon for general typing to need immediate access to all such symbols. Instead, include specific symbols, such as ones common to a programming language, via `explicit_inclusions`."));
+	options_map.insert(String::from("shift_overrides"), String::from("Keys where shift types something other than the US-ANSI default, each written as a [[layout_optimizer_config.keycode_options.shift_overrides]] table with `key` and `shifted`, e.g., key = \"_COMM\" and shifted = \"_SCLN\" if shift + comma types ;. Text is broken into keycodes accordingly, and characters that the overridden key would have typed with shift (< in this example) need their own keycode in the layout, while the shifted keycode (_SCLN in this example) is no longer generated unless it is in `explicit_inclusions`."));
+	options_map.insert(String::from("shifted"), String::from("See `shift_overrides`."));
+	options_map.insert(String::from("character_keycodes"), String::from("How to type characters that aren't on a US-ANSI keyboard, each written as a [[layout_optimizer_config.keycode_options.character_keycodes]] table with a `character` and its `keycodes`. A character can get its own keycode, e.g., keycodes = [{ _UC = \"ä\" }], written as ä in the layout; a dead key followed by a letter, e.g., keycodes = [{ _DEAD = \"´\" }, \"_E\"] for é, written as DK´ in the layout; or an AltGr combination, e.g., keycodes = [\"_ALTGR\", \"_E\"] for €. Capitals without their own entry use shift + the lowercase entry. The keycodes are added to the keycode set. Other non-ASCII characters are skipped."));
+	options_map.insert(String::from("character"), String::from("See `character_keycodes`."));
+	options_map.insert(String::from("keycodes"), String::from("See `constraints` and `character_keycodes`."));
+	options_map.insert(String::from("shift_model"), String::from("How shifted characters are typed. Held (hold SFT for each shifted character), OneShot (tap OSS, the one-shot shift keycode, before each shifted character) or CapsWord (tap CAPW, the caps word keycode, once before a run of two or more capital letters; other shifted characters use held SFT). One-shot shift and caps word are scored as taps that don't take part in same finger, alternation and roll scoring, so place OSS or CAPW in the initial layout or leave it to the optimizer, and SFT can be removed from `explicit_inclusions` with OneShot."));
 	options_map.insert(String::from("include_brackets"), String::from("Whether the various brackets ()[]{}<> should be considered their own keycodes. (Note that \"[]\" will always be considered their own keycodes since they are base, non-shifted keys.) If users prefer symmetrically placed brackets, recommended to set to true with manual initial symmetric placements. Otherwise, set to false."));
 	options_map.insert(String::from("include_misc_symbols"), String::from("Convenience toggle. -=\\;'`/[] Set to true or manually place in the layout, as these are required for typing."));
 	options_map.insert(String::from("include_misc_symbols_shifted"), String::from("Whether shifted versions of misc. symbols, i.e., _+|:\"~?{} should be considered their own keycodes. Recommended to set to false, as it is uncommon for general typing to need immediate access to all such symbols. Instead, include specific symbols via `explicit_inclusions`."));
 	options_map.insert(String::from("explicit_inclusions"), String::from("Keycodes to explicitly include, for if no combination of options covers exactly what the user wants. If not manually added to the layout, shift (SFT) should be included here."));
-	options_map.insert(String::from("dataset_paths"), String::from("Path to directories containing textual data. Currently only looks in the immediate directory and not recursively. Presets are planned."));
+	options_map.insert(String::from("dataset_paths"), String::from("Path to directories containing textual data, or to precomputed n-gram frequency tables (.csv or .tsv rows of ngram and count, or .json of the form {\"ngram\": count}). Counts can also be fractional, e.g., probabilities. Tables should have every n-gram length up to `max_ngram_size`. Files ending in .keylog are read as keystroke logs with one keycode per line, optionally preceded by a timestamp, e.g., \"0.25 _BSPC\", so editing and navigation keys get counted too. Subdirectories are included, see `include_globs` and `exclude_globs` to pick which files are read. Processed datasets are cached in the user cache directory and reprocessed when the files change, see `alc cache list` and `alc cache purge`. Bundled datasets can be used without collecting any text with \"preset:english\" (modern English prose from the Rust documentation books) or \"preset:code\" (a mix of Rust, Python, JavaScript, and C source code), which have n-grams up to length 4."));
+	options_map.insert(String::from("ngram_coverage"), String::from("Instead of a fixed number of ngrams, keep the most frequent ngrams until they make up this fraction of all occurrences, e.g., [0.95] for every ngram size or [0.99, 0.95, 0.9, 0.8] for each size starting from 1-grams. Longer ngrams are spread over many more combinations, so the same count covers less of the text. Overrides `top_n_ngrams_to_take` if non-empty. The coverage achieved is printed when the dataset is built."));
+	options_map.insert(String::from("ngram_size_weights"), String::from("Relative weight of each n-gram length when scoring, starting from 1-grams, e.g., [0.5, 1.0, 1.0, 0.5] to emphasize bigrams and trigrams. Must have `max_ngram_size` entries, none negative and at least one greater than 0. For a dataset missing some lengths, e.g., a table without 4-grams, the weights of the lengths it has are scaled up to make up the difference, so every dataset keeps its share of `dataset_weights`. If empty, every length has equal weight."));
+	options_map.insert(String::from("include_globs"), String::from("Glob patterns of the files to read from each dataset directory, relative to that directory, e.g., [\"*.rs\", \"*.md\"] to only pick up Rust and Markdown files in a source repository. If empty, every file is read."));
+	options_map.insert(String::from("exclude_globs"), String::from("Glob patterns of files and directories to skip, relative to each dataset directory, e.g., [\"target\", \"**/tests/**\"]. Takes priority over `include_globs`."));
+	options_map.insert(String::from("max_ngrams_in_memory"), String::from("While reading a dataset, once more than this many distinct n-grams of one length have been counted, the rarest ones are dropped. Keeps memory bounded on very large datasets at the cost of slightly approximate counts for rare n-grams. 0 means n-grams are never dropped."));
+	options_map.insert(String::from("line_endings"), String::from("How n-grams spanning the end of a line are handled. \"Boundary\" counts each line on its own. \"Enter\" joins lines with _ENT and \"Space\" joins them with _SPC, so text is read as one continuous stream."));
+	options_map.insert(String::from("intra_word_only"), String::from("Only count n-grams made entirely of letters and numbers, dropping any that touch spaces, punctuation, or line breaks. Useful for scoring motion within words only."));
+	options_map.insert(String::from("strip_indentation"), String::from("Drop whitespace at the start of each line, which editors usually insert for you when writing code."));
+	options_map.insert(String::from("drop_auto_paired"), String::from("Drop closing brackets and closing double quotes, which editors usually insert when the opening one is typed."));
+	options_map.insert(String::from("max_identifier_length"), String::from("Cut identifiers (words starting with a letter or _) down to this many characters, since autocomplete usually types the rest. 0 means identifiers are kept whole."));
+	options_map.insert(String::from("skip_comments"), String::from("Drop // and /* */ comments, e.g., to only score the code itself."));
+	options_map.insert(String::from("skip_string_contents"), String::from("Drop text between double quotes, including strings that span lines and raw strings such as r\"C:\\\". The quotes themselves are kept. A double quote in a char literal, '\"', doesn't start a string."));
 	options_map.insert(String::from("dataset_weights"), String::from("Ratio of datasets' importance. For example, with two datasets at a 2:1 ratio, the first dataset will constitute 2/(2 + 1) of the score and the second will constitute 1/(2 + 1)."));
 	options_map.insert(String::from("max_ngram_size"), String::from("Maximum length of ngrams to extract from text. Recommended to set to 4."));
 	options_map.insert(String::from("top_n_ngrams_to_take"), String::from("Number of most frequent ngrams to include. Some ngrams barely occur, thus having very little impact on overall score, so excluding them can decrease runtime. Applies to all ngram sizes. For example, if this value is 50, then the top 50 characters, top 50 bigrams, top 50 trigrams, etc., are taken"));
@@ -253,15 +407,28 @@ pub fn option_descriptions() -> HashMap<String, String> {
 	options_map.insert(String::from("finger_roll_weight"), String::from("See `hand_alteration_weight`."));
 	options_map.insert(String::from("finger_roll_reduction_factor"), String::from("When a sequence of at least 3 keys is a finger roll, the effort of that sequence is multiplied by this factor. Sequential keys that cross two or more rows are not eligible for rolls. Inner and outer rolls are weighed the same (for now)."));
 	options_map.insert(String::from("finger_roll_same_row_reduction_factor"), String::from("If a roll occurs where all fingers are in the same row, the effort of that sequence is multiplied by this factor, on top of the standard roll reduction factor. In other words, rolls where all keys are in the same row are extra favorable."));
+	options_map.insert(String::from("mod_tap_misfire_penalty_factor"), String::from("If a mod-tap key (e.g., A/SFT_10) is followed by a different finger on the same hand, the effort of the mod-tap key is multiplied by this factor. Fast same-hand rolls are where mod-taps tend to misfire, either typing the tap when the hold was wanted or the other way around. 1.0 means no penalty."));
+	options_map.insert(String::from("combo_penalty_factor"), String::from("A combo counts as a single press with the effort of its hardest key multiplied by this factor. Combos are scored separately from the keys around them, so they don't take part in hand alternation, rolls or same finger penalties."));
 	options_map.insert(String::from("same_finger_penalty_factor"), String::from("If the same finger (on the same hand, of course) is used twice in a row, the effort is multiplied by this factor. In other words, repeating the same finger is unfavorable."));
 	options_map.insert(String::from("extra_length_penalty_factor"), String::from("If the keycode sequence is longer than the ngram (e.g., from layer switches or shifting), the effort of that sequence is multiplied by this factor."));
 	options_map.insert(String::from("valid_keycodes"), String::from("Recommended to leave empty, as these will be generated from keycode options. If keycodes are supplied here, they will override keycode options; however, you can simply use the options + `explicit_inclusions` to fine tune the set you want, rather than having to list everything out here."));
 	options_map.insert(String::from("num_rows"), String::from("Number of rows in the layout. Note that some row x column combinations may not exist, in which case use the next size up and block key positions as necessary. Available sizes should be listed here at some point: "));
 	options_map.insert(String::from("num_cols"), String::from("Number of columns in the layout."));
-	options_map.insert(String::from("layout"), String::from("Collection of layers. Each key is of the format {{keycode}}_{{moveability flag}}{{symmetry flag}}. Keycode reference should be available here: . Moveability of 1 means the optimizer can change the key in the given position; otherwise, the key will be fixed. Symmetry of 1 means it and its corresponding symmetric key will be locked in symmetry -- if one moves, the other will be moved to the corresponding symmetric location."));
+	options_map.insert(String::from("layout"), String::from("Collection of layers. Each key is of the format {{keycode}}_{{moveability flag}}{{symmetry flag}}, optionally followed by a region number 1-9. Keycode reference should be available here: . Moveability of 1 means the optimizer can change the key in the given position; otherwise, the key will be fixed. Symmetry of 1 means it and its corresponding symmetric key will be locked in symmetry -- if one moves, the other will be moved to the corresponding symmetric location. Keys with the same region number only swap among themselves, so keycodes in a region stay within its positions; e.g., mark the left home row as A_101 E_101 __101 __101 to keep A and E there, with the blank keys filled in by the optimizer. Layer keys are LSX (held layer switch), TGX (toggle, pressed again to toggle back), OSLX (one-shot, applies to the next key only) and LTX:{{keycode}} (layer-tap, types the keycode when tapped and acts as LSX when held), where X is the target layer. The key in the same position on the target layer is reserved for the layer key and should be left blank. Mod-tap keys are written as {{keycode}}/{{modifier}}, e.g., A/SFT_10 types A when tapped and acts as SFT when held; the modifier (SFT, CTRL, ALT or GUI) stays with the position while the optimizer moves the tapped keycodes around, so _/SFT_10 leaves the tapped keycode up to the optimizer."));
+	options_map.insert(String::from("combos"), String::from("Combos (chords) type a keycode when two or more keys on the same layer are pressed together, each written as a [[layout_info.combos]] table with `positions = [[layer, row, col], ...]` and a `key` in the same format as keys in `layout`, e.g., `key = \"TAB_00\"`. A moveable combo key (e.g., `key = \"TAB_10\"` or `key = \"__10\"`) lets the optimizer choose which keycode goes on the combo. The cost of a combo is set by `combo_penalty_factor`."));
+	options_map.insert(String::from("magic"), String::from("Rules for the magic (alt-repeat) key, MAGIC in the layout, each written as a [[layout_info.magic]] table with the `previous` keycode and a `key` in the same format as keys in `layout`, e.g., previous = \"_E\" and key = \"Y_00\" makes the magic key type Y after E. A moveable key (e.g., `key = \"__10\"`) lets the optimizer choose what the magic key types after `previous`. The repeat key, REP in the layout, types the previous keycode again. Both can be placed by the optimizer by adding _REP or _MAGIC to `explicit_inclusions`."));
+	options_map.insert(String::from("previous"), String::from("See `magic`."));
+	options_map.insert(String::from("positions"), String::from("See `combos`."));
+	options_map.insert(String::from("key"), String::from("See `combos`, `magic` and `shift_overrides`."));
 	options_map.insert(String::from("effort_layer"), String::from("Specify the relative effort required to reach each key position. Smaller number means lower effort. Recommended to make the most accessible keys a weight of 1 and scale other keys accordingly. Does require some tinkering to create a grid that works for you."));
 	options_map.insert(String::from("phalanx_layer"), String::from("Specify which hand and finger you want to use for each key. Used in calculating hand alternation bonuses, roll bonuses, and same finger penalties. Format is {{hand}}:{{finger}}, with hand options (L)eft and (R)ight and finger options (T)humb, (I)ndex, (M)iddle, (R)ing, (P)inkie, and (J)oint. Joint refers to where your pinkie meets your palm; some users use this part of their hand to hit the bottom left- / bottom right-most keys."));
-	options_map.insert("num_threads".to_string(), "Number of threads to parallelize score calculation over. The user should check their CPU's spec sheet for the maximum number of threads available and reduce that count by a few to avoid issues that I don't really understand. .".to_string());
+	options_map.insert(String::from("constraints"), String::from("Rules about where keycodes are allowed to go, each written as a [[layout_optimizer_config.constraints]] table with a `type`. InOrder (`keycodes = [...]`) keeps the keycodes left to right in consecutive columns of one row. Mirrored (`left = ..., right = ...`) keeps two keycodes in symmetric positions, with `left` in the left half; combine with InOrder for adjacent pairs such as parentheses. OnHand (`keycode = ..., hand = \"Left\"`) keeps every copy of a keycode on one hand. Constrained keycodes must already be placed in the initial layout in a way that satisfies every constraint."));
+	options_map.insert(String::from("type"), String::from("Kind of constraint, see `constraints`."));
+	options_map.insert(String::from("left"), String::from("See `constraints`."));
+	options_map.insert(String::from("right"), String::from("See `constraints`."));
+	options_map.insert(String::from("keycode"), String::from("See `constraints`."));
+	options_map.insert(String::from("hand"), String::from("See `constraints`."));
+	options_map.insert("num_threads".to_string(), "Number of threads to parallelize score calculation (and islands, see `island_count`) over. The user should check their CPU's spec sheet for the maximum number of threads available and reduce that count by a few to avoid issues that I don't really understand. .".to_string());
 
 	options_map
 }
@@ -301,5 +468,30 @@ use super::*;
 		optimizer_toml_object.write_to_file("./templates/2x4.toml").unwrap();
 
 	}
+
+	#[test]
+	fn test_dataset_options_defaults() {
+		// only the fields that are required in the toml
+		let dataset_options: DatasetOptions = toml::from_str("
+			dataset_paths = [\"preset:english\"]
+			dataset_weights = [1.0]
+			max_ngram_size = 4
+			top_n_ngrams_to_take = 100
+		").unwrap();
+		assert_eq!(dataset_options, DatasetOptions::default());
+	}
+
+	#[test]
+	fn test_combos_and_magic_read_write() {
+		let mut lo: LayoutOptimizer<2, 4, AdvancedScoreFunction> = LayoutOptimizer::default();
+		let combo = Combo::try_new(vec![LayoutPosition::new(0, 1, 1), LayoutPosition::new(0, 1, 2)], KeycodeKey::try_from("TAB_10").unwrap()).unwrap();
+		lo.base_layout.set_combos(vec![combo]).unwrap();
+		lo.base_layout.set_magic_rules(vec![MagicRule::try_new(Keycode::_E, KeycodeKey::try_from("Y_10").unwrap()).unwrap()]).unwrap();
+		let optimizer_toml_string = LayoutOptimizerTomlAdapter::try_from_layout_optimizer(&lo).try_to_toml_string().unwrap();
+		assert!(optimizer_toml_string.contains("[[layout_info.combos]]"));
+		assert!(optimizer_toml_string.contains("[[layout_info.magic]]"));
+		let lo_from_toml: LayoutOptimizer<2, 4, AdvancedScoreFunction> = LayoutOptimizer::try_from_toml_string(&optimizer_toml_string).unwrap();
+		assert_eq!(lo, lo_from_toml);
+	}
 	
 }
\ No newline at end of file
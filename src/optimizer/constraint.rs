@@ -0,0 +1,178 @@
+use std::fmt;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::alc_error::AlcError;
+use crate::keyboard::key::{Hand, KeyValue, PhalanxKey};
+use crate::keyboard::layer::Layer;
+use crate::keyboard::layout::Layout;
+use crate::text_processor::keycode::Keycode;
+
+/// Rules about where keycodes are allowed to go. The base layout must already satisfy every constraint; after that, randomizing never breaks a constraint and mutations that would break one are undone.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum KeycodeConstraint {
+	/// The keycodes sit left to right in consecutive columns of a single row, e.g., _1 through _ZERO
+	InOrder { keycodes: Vec<Keycode> },
+	/// `left` and `right` sit in symmetric positions of the same row, with `left` in the left half. Combine with InOrder to also require the two to be adjacent.
+	Mirrored { left: Keycode, right: Keycode },
+	/// Every copy of `keycode` is typed by `hand`
+	OnHand { keycode: Keycode, hand: Hand },
+}
+use KeycodeConstraint::*;
+
+impl KeycodeConstraint {
+	pub fn keycodes(&self) -> Vec<Keycode> {
+		match self {
+			InOrder { keycodes } => keycodes.clone(),
+			Mirrored { left, right } => vec![*left, *right],
+			OnHand { keycode, hand: _ } => vec![*keycode],
+		}
+	}
+
+	/// Err describes how the layout violates the constraint
+	pub fn check<const R: usize, const C: usize>(&self, layout: &Layout<R, C>, phalanx_layer: &Layer<R, C, PhalanxKey>) -> Result<(), AlcError> {
+		for keycode in self.keycodes() {
+			if layout.positions_of(keycode).is_empty() {
+				return Err(AlcError::ConstraintViolationError(self.to_string(), format!("{} is not in the layout, constrained keycodes must be placed in the initial layout", keycode)));
+			}
+		}
+		match self {
+			InOrder { keycodes } => {
+				let Some(first_keycode) = keycodes.first() else {
+					return Err(AlcError::ConstraintViolationError(self.to_string(), String::from("no keycodes given")));
+				};
+				for start in layout.positions_of(*first_keycode) {
+					let in_order = keycodes.iter().enumerate().all(|(i, k)| {
+						start.col_index + i < C && layout[(start.layer_index, start.row_index, start.col_index + i)].value() == *k
+					});
+					if in_order {
+						return Ok(());
+					}
+				}
+				Err(AlcError::ConstraintViolationError(self.to_string(), String::from("no row contains these keycodes in consecutive columns")))
+			},
+			Mirrored { left, right } => {
+				for lp in layout.positions_of(*left) {
+					let lp_symmetric = layout.symmetric_position(lp);
+					if lp.col_index < lp_symmetric.col_index && layout[lp_symmetric].value() == *right {
+						return Ok(());
+					}
+				}
+				Err(AlcError::ConstraintViolationError(self.to_string(), format!("{} is never in the left half with {} in the mirrored position", left, right)))
+			},
+			OnHand { keycode, hand } => {
+				for lp in layout.positions_of(*keycode) {
+					let (found_hand, _finger) = phalanx_layer[lp].value();
+					if found_hand != *hand {
+						return Err(AlcError::ConstraintViolationError(self.to_string(), format!("found at {}, which is typed by the {} hand", lp, found_hand)));
+					}
+				}
+				Ok(())
+			},
+		}
+	}
+}
+impl fmt::Display for KeycodeConstraint {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InOrder { keycodes } => write!(f, "InOrder {:?}", keycodes),
+			Mirrored { left, right } => write!(f, "Mirrored {} / {}", left, right),
+			OnHand { keycode, hand } => write!(f, "OnHand {} ({})", keycode, hand),
+		}
+	}
+}
+
+/// Checks constraints in order and returns the first violation
+pub fn check_constraints<const R: usize, const C: usize>(constraints: &[KeycodeConstraint], layout: &Layout<R, C>, phalanx_layer: &Layer<R, C, PhalanxKey>) -> Result<(), AlcError> {
+	for constraint in constraints {
+		constraint.check(layout, phalanx_layer)?;
+	}
+	Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::text_processor::keycode::Keycode::*;
+
+	fn test_layers() -> (Layout<2, 6>, Layer<2, 6, PhalanxKey>) {
+		let layout = Layout::<2, 6>::try_from("
+			___Layer 0___
+			1_10 2_10 3_10 A_10 B_10 C_10
+			LPRN_10 E_10 __10 __10 H_10 RPRN_10
+		").unwrap();
+		let phalanx_layer = Layer::<2, 6, PhalanxKey>::try_from("
+			l:p l:m l:i r:i r:m r:p
+			l:p l:m l:i r:i r:m r:p
+		").unwrap();
+		(layout, phalanx_layer)
+	}
+
+	#[test]
+	fn test_in_order() {
+		let (layout, phalanx_layer) = test_layers();
+		assert!(InOrder { keycodes: vec![_1, _2, _3] }.check(&layout, &phalanx_layer).is_ok());
+		assert!(InOrder { keycodes: vec![_3, _A, _B, _C] }.check(&layout, &phalanx_layer).is_ok());
+		assert!(InOrder { keycodes: vec![_2, _1] }.check(&layout, &phalanx_layer).is_err());
+		// runs off the end of the row rather than wrapping
+		assert!(InOrder { keycodes: vec![_C, _LPRN] }.check(&layout, &phalanx_layer).is_err());
+		assert_eq!(
+			InOrder { keycodes: vec![] }.check(&layout, &phalanx_layer),
+			Err(AlcError::ConstraintViolationError(String::from("InOrder []"), String::from("no keycodes given")))
+		);
+		assert_eq!(
+			InOrder { keycodes: vec![_1, _4] }.check(&layout, &phalanx_layer),
+			Err(AlcError::ConstraintViolationError(String::from("InOrder [_1, _4]"), String::from("_4 is not in the layout, constrained keycodes must be placed in the initial layout")))
+		);
+	}
+
+	#[test]
+	fn test_mirrored() {
+		let (layout, phalanx_layer) = test_layers();
+		assert!(Mirrored { left: _LPRN, right: _RPRN }.check(&layout, &phalanx_layer).is_ok());
+		assert!(Mirrored { left: _RPRN, right: _LPRN }.check(&layout, &phalanx_layer).is_err());
+		assert!(Mirrored { left: _1, right: _B }.check(&layout, &phalanx_layer).is_err());
+		assert!(Mirrored { left: _E, right: _H }.check(&layout, &phalanx_layer).is_ok());
+	}
+
+	#[test]
+	fn test_on_hand() {
+		let (layout, phalanx_layer) = test_layers();
+		assert!(OnHand { keycode: _E, hand: Hand::Left }.check(&layout, &phalanx_layer).is_ok());
+		assert!(OnHand { keycode: _H, hand: Hand::Left }.check(&layout, &phalanx_layer).is_err());
+		let constraints = vec![OnHand { keycode: _E, hand: Hand::Left }, OnHand { keycode: _H, hand: Hand::Left }];
+		assert_eq!(
+			check_constraints(&constraints, &layout, &phalanx_layer),
+			Err(AlcError::ConstraintViolationError(String::from("OnHand _H (Left)"), String::from("found at (L0 R1 C4), which is typed by the Right hand")))
+		);
+	}
+
+	#[test]
+	fn test_constraints_from_toml() {
+		#[derive(Debug, Deserialize)]
+		struct Wrapper {
+			constraints: Vec<KeycodeConstraint>,
+		}
+		let w: Wrapper = toml::from_str(r#"
+			[[constraints]]
+			type = "InOrder"
+			keycodes = ["_1", "_2", "_3"]
+
+			[[constraints]]
+			type = "Mirrored"
+			left = "_LPRN"
+			right = "_RPRN"
+
+			[[constraints]]
+			type = "OnHand"
+			keycode = "_E"
+			hand = "Left"
+		"#).unwrap();
+		assert_eq!(w.constraints, vec![
+			InOrder { keycodes: vec![_1, _2, _3] },
+			Mirrored { left: _LPRN, right: _RPRN },
+			OnHand { keycode: _E, hand: Hand::Left },
+		]);
+	}
+}
@@ -0,0 +1,360 @@
+use std::path::Path;
+use serde_derive::Serialize;
+
+use crate::alc_error::AlcError;
+use crate::keyboard::key::{Finger, Hand, KeyValue};
+use crate::keyboard::layout::Layout;
+use crate::keyboard::layout_presets::{get_size_variant, LayoutSizePresets};
+use crate::keyboard::{LayoutPosition, LayoutPositionSequence};
+use crate::objective::scoring::{AdvancedScoreFunction, Score};
+use crate::text_processor::dataset::FrequencyDataset;
+use crate::text_processor::frequency_holder::Frequencies;
+use crate::text_processor::keycode::Keycode::*;
+use super::config::LayoutOptimizerTomlAdapter;
+use super::LayoutOptimizer;
+
+/// How a layout types a dataset. Everything besides `score` is a fraction of the relevant n-grams or keypresses, weighted by frequency
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+pub struct LayoutMetrics {
+	pub score: f64,
+	pub same_finger_bigrams: f64, // bigrams with two different keys pressed by the same finger in a row
+	pub rolls: f64, // trigrams typed by one hand with fingers moving steadily inward or outward, at most one row apart, out of the trigrams typed with three character presses, see [character_presses]
+	pub hand_alternation: f64, // trigrams typed left-right-left or right-left-right, out of the same trigrams as `rolls`
+	pub finger_loads: Vec<(String, f64)>, // share of keypresses per finger, e.g., ("Left Index", 0.2)
+	pub layer_usage: Vec<f64>, // share of keypresses per layer
+}
+
+/// Whether a lower or higher value of a comparison row is better, if either
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Better {
+	Lower,
+	Higher,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ComparisonRow {
+	pub name: String,
+	pub values: Vec<f64>,
+	pub better: Option<Better>,
+	pub is_fraction: bool,
+}
+impl ComparisonRow {
+	/// Indices of the best value and any values tied with it, empty if the row has no better direction or all values are the same
+	pub fn best_indices(&self) -> Vec<usize> {
+		let values = self.values.iter().filter(|v| !v.is_nan());
+		let best = match self.better {
+			Some(Better::Lower) => values.min_by(|a, b| a.total_cmp(b)),
+			Some(Better::Higher) => values.max_by(|a, b| a.total_cmp(b)),
+			None => None,
+		};
+		let best_indices = match best {
+			Some(best) => self.values.iter().enumerate().filter(|(_, v)| (*v - best).abs() < 1e-12).map(|(i, _)| i).collect(),
+			None => vec![],
+		};
+		if best_indices.len() == self.values.len() {
+			vec![]
+		} else {
+			best_indices
+		}
+	}
+	fn format_value(&self, value: f64) -> String {
+		if value.is_nan() {
+			String::from("n/a")
+		} else if self.is_fraction {
+			format!("{:.2}%", value * 100.0)
+		} else {
+			format!("{:.4}", value)
+		}
+	}
+}
+
+/// Left pinkie through left thumb, then right thumb through right pinkie
+fn finger_rank(hand: Hand, finger: Finger) -> usize {
+	let finger_index = match finger {
+		Finger::Joint => 0,
+		Finger::Pinkie => 1,
+		Finger::Ring => 2,
+		Finger::Middle => 3,
+		Finger::Index => 4,
+		Finger::Thumb => 5,
+		Finger::PlaceholderFinger => 6,
+	};
+	match hand {
+		Hand::Left => finger_index,
+		Hand::Right => 13 - finger_index,
+		Hand::PlaceholderHand => 14 + finger_index,
+	}
+}
+
+/// The positions of `sequence` that type characters, leaving out held mod-tap keys, combos, layer keys, and modifiers such as _SFT, so that shifted and layered n-grams are judged by the keys that type their characters
+fn character_presses<const R: usize, const C: usize>(layout: &Layout<R, C>, sequence: &LayoutPositionSequence) -> Vec<LayoutPosition> {
+	sequence.iter().enumerate()
+		.filter(|(i, _)| !sequence.holds().contains(i) && !sequence.combos().iter().any(|(start, end)| i >= start && i < end))
+		.filter(|(_, lp)| {
+			let keycode = layout[**lp].value();
+			!keycode.is_layer_switch() && !matches!(keycode, _LST(_, _) | _SFT | _CTRL | _ALT | _GUI | _OSS | _CAPW | _ALTGR)
+		})
+		.map(|(_, lp)| *lp)
+		.collect()
+}
+
+impl<const R: usize, const C: usize, S> LayoutOptimizer<R, C, S> where S: Score<R, C> + Send + Sync + Clone {
+	/// Scores `layout` and measures how it types `datasets`, with datasets weighted by `dataset_weights`
+	pub fn compute_metrics<T>(&self, layout: &Layout<R, C>, datasets: &[FrequencyDataset<T>]) -> Result<LayoutMetrics, AlcError> where T: Frequencies {
+		self.check_dataset_weights(datasets)?;
+		let score = self.score_datasets(layout, datasets, false)?.0;
+		let weighted_datasets = datasets.iter().zip(self.config.dataset_options.dataset_weights.iter()).map(|(d, w)| (d, *w)).collect::<Vec<(&FrequencyDataset<T>, f64)>>();
+		let blended = FrequencyDataset::blend(String::from("blended"), &weighted_datasets)?;
+
+		let mut finger_loads: Vec<(Hand, Finger, f64)> = vec![];
+		let mut layer_usage = vec![0.0; layout.len()];
+		let mut presses_total = 0.0;
+		if let Some(onegrams) = blended.get(&1) {
+			for (ngram, frequency) in onegrams.iter() {
+				for pos in self.best_sequence(layout, ngram)?.0 {
+					let (hand, finger) = self.phalanx_layer[pos].value();
+					match finger_loads.iter_mut().find(|(h, f, _)| *h == hand && *f == finger) {
+						Some((_, _, load)) => *load += frequency,
+						None => finger_loads.push((hand, finger, *frequency)),
+					}
+					layer_usage[pos.layer_index] += frequency;
+					presses_total += frequency;
+				}
+			}
+		}
+		finger_loads.sort_by_key(|(hand, finger, _)| finger_rank(*hand, *finger));
+
+		let mut same_finger_bigrams = 0.0;
+		let mut bigrams_total = 0.0;
+		if let Some(bigrams) = blended.get(&2) {
+			for (ngram, frequency) in bigrams.iter() {
+				let (sequence, _) = self.best_sequence(layout, ngram)?;
+				let is_same_finger = (1..sequence.len()).any(|i| {
+					sequence[i - 1] != sequence[i] && self.phalanx_layer[sequence[i - 1]].value() == self.phalanx_layer[sequence[i]].value()
+				});
+				if is_same_finger {
+					same_finger_bigrams += frequency;
+				}
+				bigrams_total += frequency;
+			}
+		}
+
+		let mut rolls = 0.0;
+		let mut hand_alternation = 0.0;
+		let mut trigrams_total = 0.0;
+		if let Some(trigrams) = blended.get(&3) {
+			for (ngram, frequency) in trigrams.iter() {
+				let (sequence, _) = self.best_sequence(layout, ngram)?;
+				let sequence = character_presses(layout, &sequence);
+				// e.g., a trigram with a held shift is only two characters, so it can't roll or alternate
+				if sequence.len() != 3 {
+					continue;
+				}
+				trigrams_total += frequency;
+				let (hand1, finger1) = self.phalanx_layer[sequence[0]].value();
+				let (hand2, finger2) = self.phalanx_layer[sequence[1]].value();
+				let (hand3, finger3) = self.phalanx_layer[sequence[2]].value();
+				if hand1 != hand2 && hand2 != hand3 {
+					hand_alternation += frequency;
+				} else if hand1 == hand2 && hand2 == hand3
+					&& (sequence[0].row_index as i8 - sequence[1].row_index as i8).abs() <= 1
+					&& (sequence[1].row_index as i8 - sequence[2].row_index as i8).abs() <= 1
+					&& ((finger1 < finger2 && finger2 < finger3) || (finger1 > finger2 && finger2 > finger3)) {
+					rolls += frequency;
+				}
+			}
+		}
+
+		Ok(LayoutMetrics {
+			score,
+			same_finger_bigrams: same_finger_bigrams / bigrams_total,
+			rolls: rolls / trigrams_total,
+			hand_alternation: hand_alternation / trigrams_total,
+			finger_loads: finger_loads.into_iter().map(|(hand, finger, load)| (format!("{} {}", hand, finger), load / presses_total)).collect(),
+			layer_usage: layer_usage.into_iter().map(|usage| usage / presses_total).collect(),
+		})
+	}
+}
+
+/// One row per metric, with one value per layout. Pinkie and joint loads and usage of layers other than 0 are better lower, other finger loads have no better direction
+pub fn comparison_rows(metrics: &[LayoutMetrics]) -> Vec<ComparisonRow> {
+	let row = |name: &str, better: Option<Better>, is_fraction: bool, value: &dyn Fn(&LayoutMetrics) -> f64| {
+		ComparisonRow { name: name.to_string(), values: metrics.iter().map(value).collect(), better, is_fraction }
+	};
+	let mut rows = vec![
+		row("score", Some(Better::Lower), false, &|m| m.score),
+		row("same finger bigrams", Some(Better::Lower), true, &|m| m.same_finger_bigrams),
+		row("rolls", Some(Better::Higher), true, &|m| m.rolls),
+		row("hand alternation", Some(Better::Higher), true, &|m| m.hand_alternation),
+	];
+	let mut finger_names: Vec<&String> = vec![];
+	for m in metrics {
+		for (name, _) in m.finger_loads.iter() {
+			if !finger_names.contains(&name) {
+				finger_names.push(name);
+			}
+		}
+	}
+	for finger_name in finger_names {
+		let better = if finger_name.ends_with("Pinkie") || finger_name.ends_with("Joint") { Some(Better::Lower) } else { None };
+		rows.push(row(&format!("{} load", finger_name), better, true, &|m| {
+			m.finger_loads.iter().find(|(name, _)| name == finger_name).map(|(_, load)| *load).unwrap_or(0.0)
+		}));
+	}
+	let layer_count = metrics.iter().map(|m| m.layer_usage.len()).max().unwrap_or(0);
+	for layer_index in 0..layer_count {
+		let better = if layer_index == 0 { None } else { Some(Better::Lower) };
+		rows.push(row(&format!("layer {} usage", layer_index), better, true, &|m| *m.layer_usage.get(layer_index).unwrap_or(&0.0)));
+	}
+	rows
+}
+
+/// Aligned table with the best values of each row marked with *
+pub fn format_comparison_table(names: &[String], rows: &[ComparisonRow]) -> String {
+	let mut cells: Vec<Vec<String>> = vec![];
+	let mut header = vec![String::from("metric")];
+	header.extend(names.iter().cloned());
+	cells.push(header);
+	for row in rows {
+		let best_indices = row.best_indices();
+		let mut line = vec![row.name.clone()];
+		for (i, value) in row.values.iter().enumerate() {
+			let marker = if best_indices.contains(&i) { "*" } else { " " };
+			line.push(format!("{}{}", row.format_value(*value), marker));
+		}
+		cells.push(line);
+	}
+	let column_count = cells[0].len();
+	let widths = (0..column_count).map(|j| cells.iter().map(|line| line[j].chars().count()).max().unwrap_or(0)).collect::<Vec<usize>>();
+	let mut table = String::new();
+	for line in cells {
+		let formatted = line.iter().enumerate().map(|(j, cell)| {
+			if j == 0 { format!("{:<width$}", cell, width = widths[j]) } else { format!("{:>width$}", cell, width = widths[j]) }
+		}).collect::<Vec<String>>().join("  ");
+		table.push_str(formatted.trim_end());
+		table.push('\n');
+	}
+	table
+}
+
+/// Unformatted values, one row per metric and one column per layout
+pub fn format_comparison_csv(names: &[String], rows: &[ComparisonRow]) -> String {
+	let quote = |s: &str| if s.contains(',') || s.contains('"') { format!("\"{}\"", s.replace('"', "\"\"")) } else { s.to_string() };
+	let mut csv = format!("metric,{}\n", names.iter().map(|name| quote(name)).collect::<Vec<String>>().join(","));
+	for row in rows {
+		csv.push_str(&format!("{},{}\n", quote(&row.name), row.values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(",")));
+	}
+	csv
+}
+
+fn compare_layouts<const R: usize, const C: usize>(filenames: &[String]) -> Result<Vec<LayoutMetrics>, AlcError> {
+	let lo = LayoutOptimizer::<R, C, AdvancedScoreFunction>::try_from_optimizer_toml_file(filenames[0].as_str())?;
+	let datasets = lo.compute_datasets()?;
+	let mut all_metrics = vec![];
+	for filename in filenames {
+		let layout = LayoutOptimizer::<R, C, AdvancedScoreFunction>::try_from_optimizer_toml_file(filename.as_str())?.base_layout;
+		all_metrics.push(lo.compute_metrics(&layout, &datasets)?);
+	}
+	Ok(all_metrics)
+}
+
+/// Measures the layouts of each optimizer config, e.g., a current layout and the `_00` to `_09` results of [super::optimize_from_toml]. The datasets, score options, effort layer, and phalanx layer all come from the first config so that every layout is measured the same way. All layouts must be the same size
+pub fn compare_from_toml(filenames: &[String]) -> Result<Vec<LayoutMetrics>, AlcError> {
+	if filenames.is_empty() {
+		return Err(AlcError::GenericError(String::from("no layouts to compare")));
+	}
+	let mut size = None;
+	for filename in filenames {
+		let t = LayoutOptimizerTomlAdapter::try_from_toml_file(filename.as_str())?;
+		let file_size = (t.layout_info.num_rows, t.layout_info.num_cols);
+		match size {
+			None => size = Some(file_size),
+			Some(first_size) if first_size != file_size => return Err(AlcError::GenericError(format!("{} is {} x {} but {} is {} x {}, only layouts of the same size can be compared", filenames[0], first_size.0, first_size.1, Path::new(filename).display(), file_size.0, file_size.1))),
+			_ => (),
+		}
+	}
+	match get_size_variant(size.unwrap())? {
+		LayoutSizePresets::TwoByFour => compare_layouts::<2, 4>(filenames),
+		LayoutSizePresets::FiveBySix => compare_layouts::<5, 6>(filenames),
+		LayoutSizePresets::FourByTen => compare_layouts::<4, 10>(filenames),
+		LayoutSizePresets::FourByTwelve => compare_layouts::<4, 12>(filenames),
+		LayoutSizePresets::FiveByTwelve => compare_layouts::<5, 12>(filenames),
+		LayoutSizePresets::FiveByFifteen => compare_layouts::<5, 15>(filenames),
+		LayoutSizePresets::SixByTwenty => compare_layouts::<6, 20>(filenames),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use crate::text_processor::frequency_holder::SingleGramFrequencies;
+	use crate::text_processor::keycode::KeycodeOptions;
+	use super::*;
+
+	#[test]
+	fn test_metrics_and_comparison() -> Result<(), AlcError> {
+		let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		for n in 1..=3 {
+			ngram_frequencies.insert(n, SingleGramFrequencies::try_from_string("hello", n, &KeycodeOptions::default())?.unwrap());
+		}
+		let datasets = vec![FrequencyDataset { name: String::from("test"), ngram_frequencies }];
+		let mut lo = LayoutOptimizer::<2, 4, AdvancedScoreFunction>::default();
+		lo.config.dataset_options.max_ngram_size = 3;
+		lo.config.dataset_options.ngram_size_weights = vec![];
+		// default 2x4 phalanx layer is left index, left middle, right middle, right index on both rows
+		let alternating = Layout::<2, 4>::try_from("
+			___Layer 0___
+			H_10 E_10 L_10 O_10
+			__10 __10 __10 __10
+		")?;
+		let same_finger = Layout::<2, 4>::try_from("
+			___Layer 0___
+			__10 H_10 E_10 __10
+			__10 O_10 L_10 __10
+		")?;
+
+		let metrics = lo.compute_metrics(&alternating, &datasets)?;
+		assert_eq!(metrics.same_finger_bigrams, 0.0);
+		assert_eq!(metrics.layer_usage, vec![1.0]);
+		assert!((metrics.finger_loads.iter().map(|(_, load)| load).sum::<f64>() - 1.0).abs() < 1e-12);
+		let same_finger_metrics = lo.compute_metrics(&same_finger, &datasets)?;
+		assert!(same_finger_metrics.same_finger_bigrams > 0.0);
+
+		let rows = comparison_rows(&[metrics.clone(), same_finger_metrics.clone()]);
+		assert_eq!(rows[1].name, "same finger bigrams");
+		assert_eq!(rows[1].best_indices(), vec![0]);
+		let tied = ComparisonRow { name: String::from("rolls"), values: vec![0.5, 0.2, 0.5], better: Some(Better::Higher), is_fraction: true };
+		assert_eq!(tied.best_indices(), vec![0, 2]);
+		assert!(ComparisonRow { values: vec![0.5, 0.5], ..tied }.best_indices().is_empty());
+		let names = vec![String::from("alternating"), String::from("same finger")];
+		let table = format_comparison_table(&names, &rows);
+		assert!(table.lines().nth(2).unwrap().starts_with("same finger bigrams"));
+		assert!(table.lines().nth(2).unwrap().contains("0.00%*"));
+		let csv = format_comparison_csv(&names, &rows);
+		assert!(csv.starts_with("metric,alternating,same finger\nscore,"));
+		Ok(())
+	}
+
+	#[test]
+	fn test_shifted_trigrams() -> Result<(), AlcError> {
+		let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
+		for n in 1..=3 {
+			ngram_frequencies.insert(n, SingleGramFrequencies::try_from_string("abcAbc", n, &KeycodeOptions::default())?.unwrap());
+		}
+		let datasets = vec![FrequencyDataset { name: String::from("test"), ngram_frequencies }];
+		let mut lo = LayoutOptimizer::<2, 4, AdvancedScoreFunction>::default();
+		lo.config.dataset_options.max_ngram_size = 3;
+		lo.config.dataset_options.ngram_size_weights = vec![];
+		let layout = Layout::<2, 4>::try_from("
+			___Layer 0___
+			A_10 C_10 B_10 __10
+			SFT_10 __10 __10 __10
+		")?;
+
+		// only the two "abc" trigrams are typed with three character presses, the three with the held _SFT on the left hand would otherwise count as neither
+		let metrics = lo.compute_metrics(&layout, &datasets)?;
+		assert_eq!(metrics.hand_alternation, 1.0);
+		assert_eq!(metrics.rolls, 0.0);
+		Ok(())
+	}
+}
@@ -1,4 +1,5 @@
 pub mod keycode;
 pub mod ngram;
 pub mod frequency_holder;
-pub mod dataset;
\ No newline at end of file
+pub mod dataset;
+pub mod preprocessing;
\ No newline at end of file
@@ -1,15 +1,21 @@
 use std::fs::{self, read_dir};
+use std::hash::{Hash, Hasher};
 use std::ops::Index;
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use std::time::UNIX_EPOCH;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 // use serde::Deserialize;
 use serde_derive::{Serialize, Deserialize};
 
 use crate::alc_error::AlcError;
 
-use super::frequency_holder::{Frequencies, SingleGramFrequencies, TopFrequenciesToTake};
-use super::keycode::KeycodeOptions;
+use super::frequency_holder::{parse_ngram_table, Frequencies, LineEndings, SingleGramFrequencies, TopFrequenciesToTake};
+use super::keycode::{KeycodeOptions, ShiftModel};
+use super::ngram::Ngram;
+use super::preprocessing::PreprocessingOptions;
 
 #[derive(Debug, PartialEq, thiserror::Error)]
 pub enum FrequencyDatasetError {
@@ -21,6 +27,22 @@ pub enum FrequencyDatasetError {
 // 	TheRustBook,
 // }
 
+/// Frequency tables bundled with alc, usable as `preset:<name>` in place of a dataset path. See presets/README.md for where they come from
+pub const DATASET_PRESETS: [(&str, &str); 2] = [
+	("english", include_str!("../../presets/english.csv")),
+	("code", include_str!("../../presets/code.csv")),
+];
+
+/// How the files of a dataset directory are found and read
+#[derive(Debug, PartialEq, Clone, Default, Hash)]
+pub struct ExtractionOptions {
+	pub include_globs: Vec<String>,
+	pub exclude_globs: Vec<String>,
+	pub max_ngrams_in_memory: usize, // 0 means rare n-grams are never pruned
+	pub line_endings: LineEndings,
+	pub intra_word_only: bool,
+	pub preprocessing: PreprocessingOptions,
+}
     
 type MultipleNgramFrequencies<T> = HashMap<usize, SingleGramFrequencies<T>>;
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
@@ -30,12 +52,10 @@ pub struct FrequencyDataset<T> where T: Frequencies {
 }
 
 impl FrequencyDataset<u32> {
-	fn new(name: String, ngram_frequencies: MultipleNgramFrequencies<u32>) -> Self {
-		Self {name, ngram_frequencies}
-	}
-
-	/// Because some ngrams are so infrequent and would only serve to increase computation time without affecting layout score very much, `top_n_to_take` allows you to choose how many of the most frequent ngrams you want to include.
-	pub fn try_from_dir(dir_string: &str, max_ngram_size: usize, top_frequencies_to_take: TopFrequenciesToTake, options: &KeycodeOptions) -> Result<Self, AlcError> {
+	/// Because some ngrams are so infrequent and would only serve to increase computation time without affecting layout score very much, `top_frequencies_to_take` allows you to choose how many of the most frequent ngrams you want to include, see [truncate_frequencies].
+	/// Files are found recursively and filtered with the globs in `extraction_options`, see [collect_dataset_files], then read in parallel, counting every n-gram size in one pass. Files ending in .keylog are read as keystroke logs, see [SingleGramFrequencies::try_all_sizes_from_keystroke_log].
+	/// The result is cached in [dataset_cache_dir], keyed on the options as well as the path, size, and modification time of every file read, so editing the text invalidates the cache.
+	pub fn try_from_dir(dir_string: &str, max_ngram_size: usize, top_frequencies_to_take: &[TopFrequenciesToTake], options: &KeycodeOptions, extraction_options: &ExtractionOptions) -> Result<Self, AlcError> {
 		let dir_expanded: String = shellexpand::full(dir_string).unwrap().to_string();
 		let dir = PathBuf::try_from(dir_expanded).unwrap();
 		let metadata = match dir.metadata(){
@@ -46,11 +66,41 @@ impl FrequencyDataset<u32> {
 			Err(AlcError::ExpectedDirectoryError(dir))
 		} else {
 			let dir_name = dir.file_name().unwrap();
-			let inclusions = options.explicit_inclusions.iter().map(|x| format!("{}", x)).collect::<Vec<String>>().join("_");
-			let saved_dataset_filename = format!("{}/{}_{}_{}_{}{}{}{}{}{}_{}.ron", dir.to_str().unwrap(), dir_name.to_str().unwrap(), max_ngram_size, top_frequencies_to_take, options.include_alphas as i32, options.include_numbers as i32, options.include_number_symbols as i32, options.include_brackets as i32, options.include_misc_symbols as i32, options.include_misc_symbols_shifted as i32, inclusions);
+			let mut inclusions = options.explicit_inclusions.iter().map(|x| format!("{}", x)).collect::<Vec<String>>().join("_");
+			if options.shift_model != ShiftModel::Held {
+				inclusions = format!("{}_{}", inclusions, options.shift_model);
+			}
+			for shift_override in options.shift_overrides.iter() {
+				inclusions = format!("{}_{}{}", inclusions, shift_override.key, shift_override.shifted);
+			}
+			for character_keycodes in options.character_keycodes.iter() {
+				let keycode_names = character_keycodes.keycodes.iter().map(|k| k.display_name()).collect::<Vec<String>>().join("");
+				inclusions = format!("{}_{}{}", inclusions, character_keycodes.character, keycode_names);
+			}
+			let options_key = format!("{}_{:?}_{}{}{}{}{}{}_{}", max_ngram_size, top_frequencies_to_take, options.include_alphas as i32, options.include_numbers as i32, options.include_number_symbols as i32, options.include_brackets as i32, options.include_misc_symbols as i32, options.include_misc_symbols_shifted as i32, inclusions);
+			let dataset_files = collect_dataset_files(&dir, &extraction_options.include_globs, &extraction_options.exclude_globs)?;
+
+			let mut hasher = FnvHasher::default();
+			fs::canonicalize(&dir).unwrap_or(dir.clone()).to_string_lossy().hash(&mut hasher);
+			options_key.hash(&mut hasher);
+			extraction_options.hash(&mut hasher);
+			for file in dataset_files.iter() {
+				let file_metadata = match file.metadata() {
+					Ok(v) => v,
+					Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, file))),
+				};
+				file.strip_prefix(&dir).unwrap_or(file).to_string_lossy().hash(&mut hasher);
+				file_metadata.len().hash(&mut hasher);
+				if let Ok(modified) = file_metadata.modified() {
+					modified.duration_since(UNIX_EPOCH).unwrap_or_default().hash(&mut hasher);
+				}
+			}
+			let cache_dir = dataset_cache_dir()?;
+			let top_frequencies_name = top_frequencies_to_take.iter().map(|t| t.to_string()).collect::<Vec<String>>().join("-");
+			let saved_dataset_filename = cache_dir.join(format!("{}_{}_{}_{:016x}.ron", dir_name.to_str().unwrap(), max_ngram_size, top_frequencies_name, hasher.finish()));
 
-			if Path::new(&saved_dataset_filename).exists() {
-				println!("loading dataset from {}", saved_dataset_filename);
+			if saved_dataset_filename.exists() {
+				println!("loading dataset from {}", saved_dataset_filename.display());
 				let dataset_from_read = fs::read_to_string(saved_dataset_filename).unwrap();
 				let fd = match ron::from_str(dataset_from_read.as_str()) {
 					Ok(v) => v,
@@ -59,28 +109,15 @@ impl FrequencyDataset<u32> {
 				Ok(fd)
 			} else {
 				println!("processing dataset for the first time");
-				// be a bit lazy for now and don't check for directories recursively
-				let mut ngram_frequencies: HashMap<usize, SingleGramFrequencies<u32>> = Default::default();
-				for n in 1..=max_ngram_size {
-					ngram_frequencies.insert(n, SingleGramFrequencies::new(n));
-				}
-				for n in 1..=max_ngram_size {
-					let files = read_dir(dir.clone()).unwrap();
-					for file0 in files {
-						let file = match file0 {
-							Ok(v) => v,
-							Err(e) => return Err(AlcError::GenericError(format!("{}", e))),
-						};
-						if let Some(ext) = file.path().extension() {
-							if ext.to_os_string() == "ron" {
-								continue
-							}
-						}
-						let single_gram_frequencies = SingleGramFrequencies::<u32>::try_from_file(file.path(), n, options)?;
-						ngram_frequencies.get_mut(&n).unwrap().combine_with(single_gram_frequencies).unwrap(); // 
+				let max_ngrams_in_memory = extraction_options.max_ngrams_in_memory;
+				let all_frequencies = count_files_in_chunks(&dataset_files, max_ngram_size, max_ngrams_in_memory, rayon::current_num_threads(), |file| {
+					if file.extension().is_some_and(|ext| ext == "keylog") {
+						SingleGramFrequencies::<u32>::try_all_sizes_from_keystroke_log(file, max_ngram_size, max_ngrams_in_memory)
+					} else {
+						SingleGramFrequencies::<u32>::try_all_sizes_from_file(file, max_ngram_size, options, extraction_options)
 					}
-					ngram_frequencies.get_mut(&n).unwrap().take_top_frequencies(top_frequencies_to_take.clone());
-				}
+				})?;
+				let ngram_frequencies = truncate_frequencies(all_frequencies, top_frequencies_to_take);
 				// do something about this
 				let name = dir.file_name().unwrap().to_str().unwrap().to_string();
 				let fd = FrequencyDataset::new(name, ngram_frequencies);
@@ -102,13 +139,295 @@ impl FrequencyDataset<u32> {
 		}
 	}
 
-	pub fn get(&self, k: &usize) -> Option<&SingleGramFrequencies<u32>> {
+	/// Loads a dataset cached by [FrequencyDataset::try_from_dir]. `file_string` can also be just the file name of a dataset in [dataset_cache_dir]
+	pub fn try_from_cache_file(file_string: &str) -> Result<Self, AlcError> {
+		let mut file = PathBuf::from(shellexpand::full(file_string).unwrap().to_string());
+		if !file.exists() {
+			file = dataset_cache_dir()?.join(file);
+		}
+		let dataset_from_read = match fs::read_to_string(&file) {
+			Ok(v) => v,
+			Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, file))),
+		};
+		match ron::from_str(dataset_from_read.as_str()) {
+			Ok(v) => Ok(v),
+			Err(e) => Err(AlcError::GenericError(format!("error deserializing from string: {}", e))),
+		}
+	}
+
+}
+
+impl<T> FrequencyDataset<T> where T: Frequencies {
+	fn new(name: String, ngram_frequencies: MultipleNgramFrequencies<T>) -> Self {
+		Self {name, ngram_frequencies}
+	}
+
+	pub fn get(&self, k: &usize) -> Option<&SingleGramFrequencies<T>> {
 		self.ngram_frequencies.get(k)
 	}
+
+	/// Frequencies of each n-gram size divided by their total, see [SingleGramFrequencies::normalized]
+	pub fn normalized(&self) -> FrequencyDataset<f64> {
+		FrequencyDataset {
+			name: self.name.clone(),
+			ngram_frequencies: self.ngram_frequencies.iter().map(|(n, frequencies)| (*n, frequencies.normalized())).collect(),
+		}
+	}
+
+	/// The same frequencies as f64, see [SingleGramFrequencies::into_f64]
+	pub fn into_f64(self) -> FrequencyDataset<f64> {
+		FrequencyDataset {
+			name: self.name,
+			ngram_frequencies: self.ngram_frequencies.into_iter().map(|(n, frequencies)| (n, frequencies.into_f64())).collect(),
+		}
+	}
+
+	/// n-gram sizes in the dataset, in increasing order
+	pub fn ngram_sizes(&self) -> Vec<usize> {
+		let mut ngram_sizes = self.ngram_frequencies.keys().copied().collect::<Vec<usize>>();
+		ngram_sizes.sort_unstable();
+		ngram_sizes
+	}
+
+	/// The `amount` most frequent n-grams of length `n` with their frequencies and frequencies relative to the total of the kept n-grams
+	pub fn top_ngrams(&self, n: usize, amount: usize) -> Vec<(Ngram, T, f64)> {
+		let frequencies = match self.get(&n) {
+			Some(v) => v,
+			None => return vec![],
+		};
+		let mut sorted_ngrams = frequencies.iter().map(|(ngram, frequency)| (ngram.clone(), *frequency, frequency.to_f64() / frequencies.total)).collect::<Vec<(Ngram, T, f64)>>();
+		sorted_ngrams.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+		sorted_ngrams.truncate(amount);
+		sorted_ngrams
+	}
+
+	/// The `amount` n-grams of length `n` whose relative frequencies differ the most between `self` and `other`, along with the relative frequency in each, which is relative to the n-grams kept in that dataset rather than the whole text. N-grams missing from one dataset have a relative frequency of 0 there
+	pub fn diff(&self, other: &Self, n: usize, amount: usize) -> Vec<(Ngram, f64, f64)> {
+		let relative_frequencies = |dataset: &Self| -> HashMap<Ngram, f64> {
+			match dataset.get(&n) {
+				Some(frequencies) => frequencies.iter().map(|(ngram, frequency)| (ngram.clone(), frequency.to_f64() / frequencies.total)).collect(),
+				None => HashMap::new(),
+			}
+		};
+		let self_frequencies = relative_frequencies(self);
+		let other_frequencies = relative_frequencies(other);
+		let mut differences = self_frequencies.keys().chain(other_frequencies.keys().filter(|ngram| !self_frequencies.contains_key(*ngram)))
+			.map(|ngram| (ngram.clone(), *self_frequencies.get(ngram).unwrap_or(&0.0), *other_frequencies.get(ngram).unwrap_or(&0.0)))
+			.collect::<Vec<(Ngram, f64, f64)>>();
+		differences.sort_by(|a, b| (b.1 - b.2).abs().total_cmp(&(a.1 - a.2).abs()).then_with(|| a.0.cmp(&b.0)));
+		differences.truncate(amount);
+		differences
+	}
+}
+
+/// Counts `files` with `count_file` in parallel, `chunk_size` files at a time, merging and pruning each chunk's counts before counting the next so that at most one chunk of per-file counts is held at once. Files are merged in order rather than as rayon splits the work, since pruning makes the result depend on the order
+fn count_files_in_chunks<F>(files: &[PathBuf], max_ngram_size: usize, max_ngrams_in_memory: usize, chunk_size: usize, count_file: F) -> Result<Vec<SingleGramFrequencies<u32>>, AlcError> where F: Fn(&PathBuf) -> Result<Vec<SingleGramFrequencies<u32>>, AlcError> + Sync {
+	let mut all_frequencies: Vec<SingleGramFrequencies<u32>> = (1..=max_ngram_size).map(SingleGramFrequencies::new).collect();
+	for chunk in files.chunks(chunk_size.max(1)) {
+		let chunk_frequencies: Vec<Result<Vec<SingleGramFrequencies<u32>>, AlcError>> = chunk.par_iter().map(&count_file).collect();
+		for single_file_frequencies in chunk_frequencies {
+			for (frequencies1, frequencies2) in all_frequencies.iter_mut().zip(single_file_frequencies?) {
+				frequencies1.combine_with(frequencies2)?;
+				frequencies1.prune(max_ngrams_in_memory);
+			}
+		}
+	}
+	Ok(all_frequencies)
+}
+
+/// Keeps the most frequent (i + 1)-grams of `all_frequencies[i]` according to `top_frequencies_to_take[i]`, reusing the last entry for any sizes past the end, and prints how much of the text each size still covers
+fn truncate_frequencies<T>(all_frequencies: Vec<SingleGramFrequencies<T>>, top_frequencies_to_take: &[TopFrequenciesToTake]) -> MultipleNgramFrequencies<T> where T: Frequencies {
+	let mut ngram_frequencies: MultipleNgramFrequencies<T> = Default::default();
+	for (i, mut single_gram_frequencies) in all_frequencies.into_iter().enumerate() {
+		let amount = top_frequencies_to_take.get(i).or(top_frequencies_to_take.last()).cloned().unwrap_or(TopFrequenciesToTake::All);
+		let coverage = single_gram_frequencies.take_top_frequencies(amount);
+		println!("kept {} {}-grams covering {:.1}% of occurrences", single_gram_frequencies.len(), i + 1, coverage * 100.0);
+		ngram_frequencies.insert(i + 1, single_gram_frequencies);
+	}
+	ngram_frequencies
+}
+
+/// Where processed datasets are cached, `alc/datasets` under the user's cache directory
+pub fn dataset_cache_dir() -> Result<PathBuf, AlcError> {
+	let cache_dir = match dirs::cache_dir() {
+		Some(v) => v.join("alc").join("datasets"),
+		None => return Err(AlcError::GenericError(String::from("could not find a cache directory"))),
+	};
+	match fs::create_dir_all(&cache_dir) {
+		Ok(v) => v,
+		Err(_e) => return Err(AlcError::ExpectedDirectoryError(cache_dir)),
+	}
+	Ok(cache_dir)
+}
+
+/// 64-bit FNV-1a. Unlike [std::hash::DefaultHasher], its output doesn't change between Rust releases, so cache file names stay valid across toolchain upgrades
+struct FnvHasher(u64);
+impl Default for FnvHasher {
+	fn default() -> Self {
+		FnvHasher(0xcbf29ce484222325)
+	}
+}
+impl Hasher for FnvHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= *byte as u64;
+			self.0 = self.0.wrapping_mul(0x100000001b3);
+		}
+	}
+}
+
+fn cached_dataset_files(cache_dir: &Path, dataset_name: Option<&str>) -> Result<Vec<PathBuf>, AlcError> {
+	let mut cached_files = vec![];
+	let entries = match read_dir(cache_dir) {
+		Ok(v) => v,
+		Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, cache_dir))),
+	};
+	for entry0 in entries {
+		let path = match entry0 {
+			Ok(v) => v.path(),
+			Err(e) => return Err(AlcError::GenericError(format!("{}", e))),
+		};
+		if path.extension().is_none_or(|ext| ext != "ron") {
+			continue;
+		}
+		if let Some(name) = dataset_name {
+			// names can contain _, so take off the n-gram size, top frequencies, and hash fields from the end instead
+			let file_stem = path.file_stem().unwrap().to_string_lossy().to_string();
+			if file_stem.rsplitn(4, '_').nth(3) != Some(name) {
+				continue;
+			}
+		}
+		cached_files.push(path);
+	}
+	cached_files.sort();
+	Ok(cached_files)
 }
 
-impl Index<usize> for FrequencyDataset<u32> {
-	type Output = SingleGramFrequencies<u32>;
+/// Cached datasets in `cache_dir` along with their size in bytes
+pub fn list_cached_datasets(cache_dir: &Path) -> Result<Vec<(PathBuf, u64)>, AlcError> {
+	cached_dataset_files(cache_dir, None)?.into_iter().map(|path| {
+		match path.metadata() {
+			Ok(v) => Ok((path, v.len())),
+			Err(e) => Err(AlcError::GenericError(format!("{}, {:?}", e, path))),
+		}
+	}).collect()
+}
+
+/// Deletes cached datasets in `cache_dir`, either all of them or only those made from directories named `dataset_name`. Returns the deleted files
+pub fn purge_cached_datasets(cache_dir: &Path, dataset_name: Option<&str>) -> Result<Vec<PathBuf>, AlcError> {
+	let cached_files = cached_dataset_files(cache_dir, dataset_name)?;
+	for path in cached_files.iter() {
+		match fs::remove_file(path) {
+			Ok(_) => (),
+			Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, path))),
+		}
+	}
+	Ok(cached_files)
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, AlcError> {
+	let mut builder = GlobSetBuilder::new();
+	for pattern in patterns {
+		builder.add(Glob::new(pattern)?);
+	}
+	Ok(builder.build()?)
+}
+
+/// Every file under `dir`, sorted, whose path relative to `dir` matches one of `include_globs` (or any file if there are none) and none of `exclude_globs`. Directories matching `exclude_globs` are skipped entirely. Cached datasets (.ron) are never included
+pub fn collect_dataset_files(dir: &Path, include_globs: &[String], exclude_globs: &[String]) -> Result<Vec<PathBuf>, AlcError> {
+	let include = build_glob_set(include_globs)?;
+	let exclude = build_glob_set(exclude_globs)?;
+	let mut files: Vec<PathBuf> = vec![];
+	let mut dirs_to_visit = vec![dir.to_path_buf()];
+	while let Some(current_dir) = dirs_to_visit.pop() {
+		let entries = match read_dir(&current_dir) {
+			Ok(v) => v,
+			Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, current_dir))),
+		};
+		for entry0 in entries {
+			let entry = match entry0 {
+				Ok(v) => v,
+				Err(e) => return Err(AlcError::GenericError(format!("{}", e))),
+			};
+			let path = entry.path();
+			let relative_path = path.strip_prefix(dir).unwrap_or(&path);
+			if exclude.is_match(relative_path) {
+				continue;
+			}
+			if path.is_dir() {
+				dirs_to_visit.push(path);
+				continue;
+			}
+			if path.extension().is_some_and(|ext| ext == "ron") {
+				continue;
+			}
+			if include_globs.is_empty() || include.is_match(relative_path) {
+				files.push(path);
+			}
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
+impl FrequencyDataset<f64> {
+	/// Weighted merge of normalized datasets. Weights are relative, so [(a, 1.0), (b, 3.0)] is a quarter `a` and three quarters `b`. Scoring the result gives the same score as scoring the datasets separately with these as `dataset_weights`
+	pub fn blend<T>(name: String, weighted_datasets: &[(&FrequencyDataset<T>, f64)]) -> Result<Self, AlcError> where T: Frequencies {
+		let weights_total: f64 = weighted_datasets.iter().map(|(_, weight)| weight).sum();
+		let mut ngram_frequencies: MultipleNgramFrequencies<f64> = Default::default();
+		for (dataset, weight) in weighted_datasets {
+			for (n, frequencies) in dataset.ngram_frequencies.iter() {
+				ngram_frequencies.entry(*n).or_insert(SingleGramFrequencies::new(*n)).add_weighted(&frequencies.normalized(), weight / weights_total)?;
+			}
+		}
+		Ok(FrequencyDataset { name, ngram_frequencies })
+	}
+
+	/// Loads a precomputed n-gram table (.csv, .tsv, or .json) exported by another analyzer, see [SingleGramFrequencies::try_all_sizes_from_table]. Tables are not cached since they are already aggregated, and are f64 since they can hold fractional frequencies
+	pub fn try_from_table(file_string: &str, max_ngram_size: usize, top_frequencies_to_take: &[TopFrequenciesToTake], options: &KeycodeOptions) -> Result<Self, AlcError> {
+		let file = PathBuf::from(shellexpand::full(file_string).unwrap().to_string());
+		let all_frequencies = SingleGramFrequencies::<f64>::try_all_sizes_from_table(&file, max_ngram_size, options)?;
+		let ngram_frequencies = truncate_frequencies(all_frequencies, top_frequencies_to_take);
+		let name = file.file_stem().unwrap_or_default().to_string_lossy().to_string();
+		Ok(FrequencyDataset::new(name, ngram_frequencies))
+	}
+
+	/// Loads one of the [DATASET_PRESETS] by name. Error if `max_ngram_size` is larger than the longest n-grams in the preset
+	pub fn try_from_preset(preset_name: &str, max_ngram_size: usize, top_frequencies_to_take: &[TopFrequenciesToTake], options: &KeycodeOptions) -> Result<Self, AlcError> {
+		let table_text = match DATASET_PRESETS.iter().find(|(name, _)| *name == preset_name) {
+			Some((_, table_text)) => table_text,
+			None => return Err(AlcError::DatasetPresetError(preset_name.to_string(), DATASET_PRESETS.iter().map(|(name, _)| *name).collect::<Vec<&str>>().join(", "))),
+		};
+		let rows = parse_ngram_table(table_text, "csv", Path::new(&format!("preset:{}", preset_name)))?;
+		// larger sizes would be empty but still get their share of the score
+		let preset_max_ngram_size = rows.iter().map(|(text, _)| text.chars().count()).max().unwrap_or(0);
+		if max_ngram_size > preset_max_ngram_size {
+			return Err(AlcError::DatasetPresetNgramSizeError(preset_name.to_string(), preset_max_ngram_size, max_ngram_size));
+		}
+		let all_frequencies = SingleGramFrequencies::<f64>::try_all_sizes_from_rows(rows, max_ngram_size, options)?;
+		let ngram_frequencies = truncate_frequencies(all_frequencies, top_frequencies_to_take);
+		Ok(FrequencyDataset::new(preset_name.to_string(), ngram_frequencies))
+	}
+
+	/// [FrequencyDataset::try_from_preset] if `path_string` is `preset:<name>`, [FrequencyDataset::try_from_table] if it is an n-gram table, otherwise [FrequencyDataset::try_from_dir], whose counts are converted to f64
+	pub fn try_from_path(path_string: &str, max_ngram_size: usize, top_frequencies_to_take: &[TopFrequenciesToTake], options: &KeycodeOptions, extraction_options: &ExtractionOptions) -> Result<Self, AlcError> {
+		if let Some(preset_name) = path_string.strip_prefix("preset:") {
+			return Self::try_from_preset(preset_name, max_ngram_size, top_frequencies_to_take, options);
+		}
+		let is_table = ["csv", "tsv", "json"].iter().any(|ext| path_string.to_lowercase().ends_with(&format!(".{}", ext)));
+		if is_table {
+			Self::try_from_table(path_string, max_ngram_size, top_frequencies_to_take, options)
+		} else {
+			Ok(FrequencyDataset::try_from_dir(path_string, max_ngram_size, top_frequencies_to_take, options, extraction_options)?.into_f64())
+		}
+	}
+}
+
+impl<T> Index<usize> for FrequencyDataset<T> where T: Frequencies {
+	type Output = SingleGramFrequencies<T>;
 	fn index(&self, index: usize) -> &Self::Output {
 		&self.ngram_frequencies[&index]
 	}
@@ -124,9 +443,144 @@ mod tests {
 	use TopFrequenciesToTake::*;
 	
 
+	#[test]
+	fn test_collect_dataset_files() -> Result<(), AlcError> {
+		let dir = std::env::temp_dir().join(format!("alc_test_collect_dataset_files_{}", std::process::id()));
+		fs::create_dir_all(dir.join("src/nested")).unwrap();
+		fs::create_dir_all(dir.join("target")).unwrap();
+		for file in ["README.md", "cached.ron", "src/lib.rs", "src/nested/mod.rs", "src/notes.txt", "target/build.rs"] {
+			fs::write(dir.join(file), "abc").unwrap();
+		}
+
+		let all_files = collect_dataset_files(&dir, &[], &[])?;
+		assert_eq!(all_files.len(), 5);
+		assert!(!all_files.contains(&dir.join("cached.ron")));
+
+		let include_globs = vec![String::from("**/*.rs"), String::from("*.md")];
+		let exclude_globs = vec![String::from("target")];
+		let filtered_files = collect_dataset_files(&dir, &include_globs, &exclude_globs)?;
+		assert_eq!(filtered_files, vec![dir.join("README.md"), dir.join("src/lib.rs"), dir.join("src/nested/mod.rs")]);
+
+		assert!(collect_dataset_files(&dir, &[String::from("[")], &[]).is_err());
+		fs::remove_dir_all(dir).unwrap();
+		Ok(())
+	}
+
+	#[test]
+	fn test_count_files_in_chunks() -> Result<(), AlcError> {
+		let files = ["ab", "bc", "cd", "de", "ea", "ab", "bb"].iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+		let started = std::sync::Mutex::new(0);
+		let count_file = |file: &PathBuf| {
+			let index = files.iter().position(|f| std::ptr::eq(f, file)).unwrap();
+			let mut started = started.lock().unwrap();
+			*started += 1;
+			// files of the next chunk only start once this chunk has been merged
+			assert!(*started <= (index / 3 + 1) * 3);
+			Ok(vec![SingleGramFrequencies::try_from_string(file.to_str().unwrap(), 1, &KeycodeOptions::default())?.unwrap()])
+		};
+		let chunked = count_files_in_chunks(&files, 1, 4, 3, count_file)?;
+		assert_eq!(*started.lock().unwrap(), 7);
+		let one_at_a_time = count_files_in_chunks(&files, 1, 4, 1, |file| Ok(vec![SingleGramFrequencies::try_from_string(file.to_str().unwrap(), 1, &KeycodeOptions::default())?.unwrap()]))?;
+		assert_eq!(chunked, one_at_a_time);
+		// pruned down to nothing after "de", so only the last three files are left
+		assert_eq!(chunked[0][Ngram::new(vec![_B])], 3);
+		assert_eq!(chunked[0].total, 6.0);
+		Ok(())
+	}
+
+	#[test]
+	fn test_fnv_hasher() {
+		let mut hasher = FnvHasher::default();
+		assert_eq!(hasher.finish(), 0xcbf29ce484222325);
+		hasher.write(b"a");
+		assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+	}
+
+	#[test]
+	fn test_list_and_purge_cached_datasets() -> Result<(), AlcError> {
+		let cache_dir = std::env::temp_dir().join(format!("alc_test_cached_datasets_{}", std::process::id()));
+		fs::create_dir_all(&cache_dir).unwrap();
+		for file in ["rust_book_4_100_0123456789abcdef.ron", "rust_book_3_All_0123456789abcdef.ron", "rust_4_100_0123456789abcdef.ron", "book_4_100_0123456789abcdef.ron", "current_step.txt"] {
+			fs::write(cache_dir.join(file), "abc").unwrap();
+		}
+
+		let cached_datasets = list_cached_datasets(&cache_dir)?;
+		assert_eq!(cached_datasets.len(), 4);
+		assert!(cached_datasets.iter().all(|(_, size)| *size == 3));
+
+		// names are matched exactly, so purging rust doesn't touch rust_book
+		let purged = purge_cached_datasets(&cache_dir, Some("rust"))?;
+		assert_eq!(purged, vec![cache_dir.join("rust_4_100_0123456789abcdef.ron")]);
+		let purged = purge_cached_datasets(&cache_dir, Some("rust_book"))?;
+		assert_eq!(purged.len(), 2);
+		assert_eq!(list_cached_datasets(&cache_dir)?, vec![(cache_dir.join("book_4_100_0123456789abcdef.ron"), 3)]);
+		purge_cached_datasets(&cache_dir, None)?;
+		assert!(list_cached_datasets(&cache_dir)?.is_empty());
+		assert!(cache_dir.join("current_step.txt").exists());
+		fs::remove_dir_all(cache_dir).unwrap();
+		Ok(())
+	}
+
+	#[test]
+	fn test_presets() -> Result<(), AlcError> {
+		for (name, _) in DATASET_PRESETS {
+			let dataset = FrequencyDataset::try_from_path(&format!("preset:{}", name), 4, &[All], &KeycodeOptions::default(), &ExtractionOptions::default())?;
+			assert_eq!(dataset.ngram_sizes(), vec![1, 2, 3, 4]);
+			assert!(dataset.ngram_frequencies.values().all(|frequencies| !frequencies.is_empty()));
+		}
+		let english = FrequencyDataset::try_from_preset("english", 2, &[Num(1)], &KeycodeOptions::default())?;
+		assert_eq!(english.top_ngrams(1, 1)[0].0, Ngram::new(vec![_SPC]));
+		assert!(matches!(FrequencyDataset::try_from_preset("klingon", 2, &[All], &KeycodeOptions::default()), Err(AlcError::DatasetPresetError(_, _))));
+		assert_eq!(FrequencyDataset::try_from_preset("english", 5, &[All], &KeycodeOptions::default()), Err(AlcError::DatasetPresetNgramSizeError(String::from("english"), 4, 5)));
+		Ok(())
+	}
+
+	#[test]
+	fn test_normalize_and_blend() -> Result<(), AlcError> {
+		let mut prose_frequencies: MultipleNgramFrequencies<u32> = Default::default();
+		prose_frequencies.insert(1, SingleGramFrequencies::try_from_string("abab", 1, &KeycodeOptions::default())?.unwrap());
+		let prose = FrequencyDataset::new(String::from("prose"), prose_frequencies);
+		let mut code_frequencies: MultipleNgramFrequencies<u32> = Default::default();
+		code_frequencies.insert(1, SingleGramFrequencies::try_from_string("bcccc", 1, &KeycodeOptions::default())?.unwrap());
+		let code = FrequencyDataset::new(String::from("code"), code_frequencies);
+
+		let normalized = prose.normalized();
+		assert_eq!(normalized[1][Ngram::new(vec![_A])], 0.5);
+		assert_eq!(normalized[1].total, 1.0);
+
+		let blended = FrequencyDataset::blend(String::from("blended"), &[(&prose, 1.0), (&code, 3.0)])?;
+		for (keycode, expected) in [(_A, 0.125), (_B, 0.125 + 0.15), (_C, 0.6)] {
+			assert!((blended[1][Ngram::new(vec![keycode])] - expected).abs() < 1e-12);
+		}
+		assert!((blended[1].total - 1.0).abs() < 1e-12);
+		assert_eq!(blended.top_ngrams(1, 1)[0].0, Ngram::new(vec![_C]));
+		Ok(())
+	}
+
+	#[test]
+	fn test_show_and_diff() -> Result<(), AlcError> {
+		let mut prose_frequencies: MultipleNgramFrequencies<u32> = Default::default();
+		prose_frequencies.insert(1, SingleGramFrequencies::try_from_string("the cat", 1, &KeycodeOptions::default())?.unwrap());
+		prose_frequencies.insert(2, SingleGramFrequencies::try_from_string("the cat", 2, &KeycodeOptions::default())?.unwrap());
+		let prose = FrequencyDataset::new(String::from("prose"), prose_frequencies);
+		let mut code_frequencies: MultipleNgramFrequencies<u32> = Default::default();
+		code_frequencies.insert(1, SingleGramFrequencies::try_from_string("a(b);", 1, &KeycodeOptions::default())?.unwrap());
+		let code = FrequencyDataset::new(String::from("code"), code_frequencies);
+
+		assert_eq!(prose.ngram_sizes(), vec![1, 2]);
+		assert_eq!(prose.top_ngrams(1, 2), vec![(Ngram::new(vec![_T]), 2, 2.0 / 7.0), (Ngram::new(vec![_A]), 1, 1.0 / 7.0)]);
+		assert!(prose.top_ngrams(3, 2).is_empty());
+
+		let differences = prose.diff(&code, 1, 3);
+		assert_eq!(differences.len(), 3);
+		assert_eq!(differences[0], (Ngram::new(vec![_T]), 2.0 / 7.0, 0.0));
+		assert_eq!(prose.diff(&code, 2, 100).len(), 6);
+		Ok(())
+	}
+
 	#[test]
 	fn test_from_directory() -> Result<(), AlcError> {
-		let frequency_dataset = FrequencyDataset::try_from_dir("./data/rust_book_test/", 4, All, &KeycodeOptions::default())?;
+		let frequency_dataset = FrequencyDataset::try_from_dir("./data/rust_book_test/", 4, &[All], &KeycodeOptions::default(), &ExtractionOptions::default())?;
 		let dataset_text = fs::read_to_string("./data/rust_book_test/rust_book_test_4_All_100010__SPC__SFT__ENT__TAB.ron").unwrap();
 		let frequency_dataset2 = ron::from_str(&dataset_text).unwrap();
 		assert_eq!(frequency_dataset, frequency_dataset2);
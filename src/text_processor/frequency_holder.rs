@@ -1,25 +1,40 @@
 use core::fmt;
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::{self, BufRead};
-use std::ops::Index;
+use std::ops::{AddAssign, Index};
 use std::collections::hash_map::{IntoIter, IntoKeys, Iter};
 use std::fs::File;
 use std::path::Path;
 
 use crate::alc_error::AlcError;
 
+use super::dataset::ExtractionOptions;
+use super::preprocessing::Preprocessor;
 use super::keycode::{Keycode, KeycodeOptions};
 use super::ngram::Ngram;
 
-pub trait Frequencies {}
-impl Frequencies for f64 {}
-impl Frequencies for u32 {}
+/// u32 for raw counts, f64 for normalized or blended frequencies
+pub trait Frequencies: Copy + Send + Sync + Default + AddAssign {
+	fn to_f64(self) -> f64;
+}
+impl Frequencies for f64 {
+	fn to_f64(self) -> f64 {
+		self
+	}
+}
+impl Frequencies for u32 {
+	fn to_f64(self) -> f64 {
+		self as f64
+	}
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TopFrequenciesToTake {
 	All,
 	Num(usize),
+	/// Keep the most frequent n-grams until they make up this fraction of all occurrences
+	Coverage(f64),
 }
 
 use serde_derive::{Deserialize, Serialize};
@@ -29,23 +44,39 @@ impl fmt::Display for TopFrequenciesToTake {
 		match self {
 			All => write!(f, "All"),
 			Num(i) => write!(f, "{}", i),
+			Coverage(c) => write!(f, "C{}", c),
 		}
 	}
 }
 
+/// What happens to the n-grams spanning the end of a line when reading a file
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy, strum_macros::Display, Serialize, Deserialize)]
+pub enum LineEndings {
+	/// Each line is counted on its own and no n-gram spans two lines
+	#[default]
+	Boundary,
+	/// Lines are joined by the keycodes of '\n', usually _ENT
+	Enter,
+	/// Lines are joined by the keycodes of ' ', usually _SPC, as when reflowing wrapped prose
+	Space,
+}
+
 /// single as in only holds one length of n-gram
 #[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
 pub struct SingleGramFrequencies<T> where T: Frequencies {
 	frequencies: HashMap<Ngram, T>,
 	n: usize,
 	pub total: f64,
+	#[serde(default)]
+	pruned_total: f64, // occurrences of n-grams dropped by prune, which are no longer in total
 }
 impl<T> SingleGramFrequencies<T> where T: Frequencies {
 	pub fn new(n: usize) -> Self {
 		Self { 
 			frequencies:  Default::default(), 
 			n,
-			total: 0.0 
+			total: 0.0,
+			pruned_total: 0.0,
 		}
 	}
 	pub fn get(&self, k: &Ngram) -> Option<&T> {
@@ -57,57 +88,187 @@ impl<T> SingleGramFrequencies<T> where T: Frequencies {
 	pub fn iter(&self) -> Iter<'_, Ngram, T> {
 		self.frequencies.iter()
 	}
-}
-
-/// u32 for raw ngram counts
-/// should only contain one ngram length
-impl SingleGramFrequencies<u32> {
-	// fn increment(&mut self, ngram: Ngram) -> Result<(), AlcError> {
-	// 	if self.n != ngram.clone().len() {
-	// 		Err(AlcError::NgramMatchError(ngram.len(), self.n))
-	// 	} else {
-	// 		Ok(*self.frequencies.entry(ngram).or_insert(0) += 1)
-	// 	}
-	// }
-
+	pub fn len(&self) -> usize {
+		self.frequencies.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.frequencies.is_empty()
+	}
+	/// Each frequency divided by the total, so that they add up to 1
+	pub fn normalized(&self) -> SingleGramFrequencies<f64> {
+		let total = self.total;
+		SingleGramFrequencies {
+			frequencies: self.frequencies.iter().map(|(ngram, frequency)| (ngram.clone(), if total > 0.0 { frequency.to_f64() / total } else { 0.0 })).collect(),
+			n: self.n,
+			total: if total > 0.0 { 1.0 } else { 0.0 },
+			pruned_total: 0.0,
+		}
+	}
+	/// The same frequencies as f64, for mixing counted datasets with ones that are already f64
+	pub fn into_f64(self) -> SingleGramFrequencies<f64> {
+		SingleGramFrequencies {
+			frequencies: self.frequencies.into_iter().map(|(ngram, frequency)| (ngram, frequency.to_f64())).collect(),
+			n: self.n,
+			total: self.total,
+			pruned_total: self.pruned_total,
+		}
+	}
 	/// Error if trying to add an Ngram with a different length
-	fn add_from_key_value(&mut self, key: Ngram, value: u32) -> Result<(), AlcError> {
+	fn add_from_key_value(&mut self, key: Ngram, value: T) -> Result<(), AlcError> {
 		if self.n != key.len() {
 			Err(AlcError::NgramMatchError(key.len(), self.n))
 		} else {
-			self.total += value as f64;
-			*self.frequencies.entry(key).or_insert(0) += value;
+			self.total += value.to_f64();
+			*self.frequencies.entry(key).or_default() += value;
 			Ok(())
 		}
 	}
 	/// This might be faster if the bigger holder is on the left?
 	/// consumes other (I think?)
 	/// will give an error if trying to combine containers with different ngram lengths
-	pub fn combine_with(&mut self, other: Self) -> Result<(), AlcError> {
+	pub fn combine_with(&mut self, mut other: Self) -> Result<(), AlcError> {
+		if other.len() > self.len() {
+			std::mem::swap(self, &mut other);
+		}
+		self.pruned_total += other.pruned_total;
 		for (key, value) in other {
 			self.add_from_key_value(key, value)?;
 		}
 		Ok(())
 	}
 
-	pub fn take_top_frequencies(&mut self, amount: TopFrequenciesToTake) {
-		let mut hash_vec: Vec<(&Ngram, &u32)> = self.frequencies.iter().collect();
-    	hash_vec.sort_by(|a, b| b.1.cmp(a.1));
+	/// Returns the fraction of occurrences covered by the n-grams that were kept, counting occurrences dropped by [SingleGramFrequencies::prune] as not covered. Ties are broken by n-gram so that the result doesn't depend on hash order
+	pub fn take_top_frequencies(&mut self, amount: TopFrequenciesToTake) -> f64 {
+		let mut hash_vec: Vec<(&Ngram, &T)> = self.frequencies.iter().collect();
+    	hash_vec.sort_by(|a, b| b.1.to_f64().total_cmp(&a.1.to_f64()).then_with(|| a.0.cmp(b.0)));
 		let amount_to_take = match amount {
 			All => hash_vec.len(),
 			Num(n) => min(hash_vec.len(), n),
+			Coverage(c) => {
+				let target = c * (self.total + self.pruned_total);
+				let mut covered = 0.0;
+				let mut amount_to_take = 0;
+				for (_, v) in hash_vec.iter() {
+					if covered >= target {
+						break;
+					}
+					covered += v.to_f64();
+					amount_to_take += 1;
+				}
+				amount_to_take
+			},
 		};
-		let mut temp_freqs: HashMap<Ngram, u32> = Default::default();
+		let old_total = self.total + self.pruned_total;
+		let mut temp_freqs: HashMap<Ngram, T> = Default::default();
 		let mut new_total = 0.0;
 		for item in hash_vec.iter().take(amount_to_take) {
 			// println!("{:?}", item);
 			let k = item.0.clone();
 			let v = *item.1;
-			new_total += v as f64;
+			new_total += v.to_f64();
 			temp_freqs.insert(k, v);
 		}
 		self.frequencies = temp_freqs;
 		self.total = new_total;
+		self.pruned_total = 0.0;
+		if old_total > 0.0 { new_total / old_total } else { 1.0 }
+	}
+
+	/// Counts every n-gram size from 1 to `max_ngram_size` from (text n-gram, count) rows. Index i holds the (i + 1)-grams.
+	/// Each row's characters are converted to keycodes one at a time, and only the keycode n-grams starting in its first character and ending in its last character are counted. Shorter n-grams are counted by the rows for shorter text n-grams instead, so the counts match those from the original text as long as the table has every n-gram length up to `max_ngram_size`
+	pub fn try_all_sizes_from_rows(rows: Vec<(String, T)>, max_ngram_size: usize, options: &KeycodeOptions) -> Result<Vec<Self>, AlcError> {
+		let mut all_ngram_to_counts = (1..=max_ngram_size).map(Self::new).collect::<Vec<Self>>();
+		for (text, count) in rows {
+			let mut char_keycodes: Vec<Vec<Keycode>> = vec![];
+			for c in text.chars() {
+				char_keycodes.push(Keycode::from_string(c.to_string().as_str(), options)?);
+			}
+			let (first_len, last_len) = match (char_keycodes.first(), char_keycodes.last()) {
+				(Some(first), Some(last)) => (first.len(), last.len()),
+				_ => continue,
+			};
+			let keycodes = char_keycodes.concat();
+			let last_start = keycodes.len() - last_len;
+			for start in 0..first_len {
+				let min_end = if char_keycodes.len() == 1 { start + 1 } else { last_start + 1 };
+				for end in min_end.max(start + 1)..=keycodes.len() {
+					if end - start > max_ngram_size {
+						break;
+					}
+					all_ngram_to_counts[end - start - 1].add_from_key_value(Ngram::new(keycodes[start..end].to_vec()), count)?;
+				}
+			}
+		}
+		Ok(all_ngram_to_counts)
+	}
+
+}
+
+/// f64 for normalized frequencies, which can be blended
+impl SingleGramFrequencies<f64> {
+	/// Adds `weight` times each of `other`'s frequencies. Error if `other` holds a different length of n-gram
+	pub fn add_weighted(&mut self, other: &SingleGramFrequencies<f64>, weight: f64) -> Result<(), AlcError> {
+		if self.n != other.n {
+			return Err(AlcError::NgramMatchError(other.n, self.n));
+		}
+		for (ngram, frequency) in other.iter() {
+			*self.frequencies.entry(ngram.clone()).or_insert(0.0) += weight * frequency;
+		}
+		self.total += weight * other.total;
+		Ok(())
+	}
+
+	/// Counts every n-gram size from 1 to `max_ngram_size` from a table of precomputed (text n-gram, frequency) rows, see [read_ngram_table] and [SingleGramFrequencies::try_all_sizes_from_rows]
+	pub fn try_all_sizes_from_table<P>(filename: P, max_ngram_size: usize, options: &KeycodeOptions) -> Result<Vec<SingleGramFrequencies<f64>>, AlcError> where P: AsRef<Path> {
+		Self::try_all_sizes_from_rows(read_ngram_table(filename)?, max_ngram_size, options)
+	}
+}
+
+/// u32 for raw ngram counts
+/// should only contain one ngram length
+impl SingleGramFrequencies<u32> {
+	// fn increment(&mut self, ngram: Ngram) -> Result<(), AlcError> {
+	// 	if self.n != ngram.clone().len() {
+	// 		Err(AlcError::NgramMatchError(ngram.len(), self.n))
+	// 	} else {
+	// 		Ok(*self.frequencies.entry(ngram).or_insert(0) += 1)
+	// 	}
+	// }
+
+	/// Counts every n-gram ending at one of `keycodes`, continuing from the `recent` keycodes of the text before them. `all_ngram_to_counts[i]` holds the (i + 1)-grams
+	fn add_from_stream(all_ngram_to_counts: &mut [Self], recent: &mut VecDeque<Keycode>, keycodes: &[Keycode]) {
+		let max_ngram_size = all_ngram_to_counts.len();
+		for keycode in keycodes {
+			if recent.len() == max_ngram_size {
+				recent.pop_front();
+			}
+			recent.push_back(*keycode);
+			for (i, ngram_to_counts) in all_ngram_to_counts.iter_mut().enumerate() {
+				let n = i + 1;
+				if recent.len() < n {
+					break;
+				}
+				let ngram = Ngram::new(recent.range(recent.len() - n..).copied().collect());
+				*ngram_to_counts.frequencies.entry(ngram).or_insert(0) += 1;
+				ngram_to_counts.total += 1.0;
+			}
+		}
+	}
+
+	/// Once there are more than `max_len` distinct n-grams, drops the rarest ones until at most half of `max_len` remain, so that memory stays bounded on large corpora. Pruned n-grams start counting from zero if they are seen again, so counts become approximate. 0 means never prune
+	pub fn prune(&mut self, max_len: usize) {
+		if max_len == 0 || self.frequencies.len() <= max_len {
+			return;
+		}
+		let target_len = (max_len / 2).max(1);
+		let mut min_count = 1;
+		while self.frequencies.len() > target_len {
+			self.frequencies.retain(|_, v| *v > min_count);
+			min_count *= 2;
+		}
+		let kept_total = self.frequencies.values().map(|v| *v as f64).sum();
+		self.pruned_total += self.total - kept_total;
+		self.total = kept_total;
 	}
 
 	/// might want to rename because it isn't really a conversion, once something turns into frequencies it can't be turned back
@@ -133,6 +294,7 @@ impl SingleGramFrequencies<u32> {
 			frequencies: ngram_to_counts, 
 			n, 
 			total,
+			pruned_total: 0.0,
 		}))
 	}
 
@@ -151,17 +313,160 @@ impl SingleGramFrequencies<u32> {
 		Ok(ngram_to_counts)
 	}
 
+	/// Counts every n-gram size from 1 to `max_ngram_size` in a single pass over the file, where index i holds the (i + 1)-grams. Lines are joined according to `extraction_options.line_endings`, and with `extraction_options.intra_word_only` only n-grams made entirely of alphanumeric characters are counted. Each line is first rewritten according to `extraction_options.preprocessing`. Each size is pruned to `extraction_options.max_ngrams_in_memory` as it goes, see [SingleGramFrequencies::prune]
+	pub fn try_all_sizes_from_file<P>(filename: P, max_ngram_size: usize, options: &KeycodeOptions, extraction_options: &ExtractionOptions) -> Result<Vec<SingleGramFrequencies<u32>>, AlcError> where P: AsRef<Path> {
+		let file = match File::open(filename) {
+			Ok(v) => v,
+			Err(e) => return Err(AlcError::GenericError(format!("{}", e))),
+		};
+		let lines = io::BufReader::new(file).lines();
+		let line_ending_keycodes = match extraction_options.line_endings {
+			LineEndings::Boundary => None,
+			LineEndings::Enter => Some(Keycode::from_string("\n", options)?),
+			LineEndings::Space => Some(Keycode::from_string(" ", options)?),
+		};
+		let mut all_ngram_to_counts = (1..=max_ngram_size).map(Self::new).collect::<Vec<Self>>();
+		let mut recent: VecDeque<Keycode> = VecDeque::with_capacity(max_ngram_size);
+		let mut preprocessor = Preprocessor::new(&extraction_options.preprocessing);
+		let preprocessing_active = extraction_options.preprocessing.is_active();
+		for line in lines.map_while(Result::ok) {
+			let line = if preprocessing_active { preprocessor.process_line(&line) } else { line };
+			if extraction_options.intra_word_only {
+				for word in line.split(|c: char| !c.is_alphanumeric()) {
+					Self::add_from_stream(&mut all_ngram_to_counts, &mut recent, &Keycode::from_string(word, options)?);
+					recent.clear();
+				}
+			} else {
+				Self::add_from_stream(&mut all_ngram_to_counts, &mut recent, &Keycode::from_string(line.as_str(), options)?);
+				match &line_ending_keycodes {
+					Some(keycodes) => Self::add_from_stream(&mut all_ngram_to_counts, &mut recent, keycodes),
+					None => recent.clear(),
+				}
+			}
+			for ngram_to_counts in all_ngram_to_counts.iter_mut() {
+				ngram_to_counts.prune(extraction_options.max_ngrams_in_memory);
+			}
+		}
+		Ok(all_ngram_to_counts)
+	}
+
+	/// Counts every n-gram size from 1 to `max_ngram_size` from a keystroke log, where index i holds the (i + 1)-grams. Each line of the log is one keycode, e.g., `_BSPC` or `BSPC`, optionally preceded by a timestamp, e.g., `1718000000.25 _LEFT`. Blank lines and lines starting with # are skipped.
+	/// Unlike text, keycodes are taken as they are, so editing and navigation keys are counted like any other key
+	pub fn try_all_sizes_from_keystroke_log<P>(filename: P, max_ngram_size: usize, max_ngrams_in_memory: usize) -> Result<Vec<SingleGramFrequencies<u32>>, AlcError> where P: AsRef<Path> {
+		let path = filename.as_ref();
+		let file = match File::open(path) {
+			Ok(v) => v,
+			Err(e) => return Err(AlcError::GenericError(format!("{}", e))),
+		};
+		let lines = io::BufReader::new(file).lines();
+		let mut all_ngram_to_counts = (1..=max_ngram_size).map(Self::new).collect::<Vec<Self>>();
+		let mut recent: VecDeque<Keycode> = VecDeque::with_capacity(max_ngram_size);
+		for (i, line) in lines.map_while(Result::ok).enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let fields = line.split_whitespace().collect::<Vec<&str>>();
+			let name = match fields.as_slice() {
+				[name] => *name,
+				[timestamp, name] if timestamp.parse::<f64>().is_ok() => *name,
+				_ => return Err(AlcError::KeystrokeLogError(path.to_path_buf(), format!("line {} should be a keycode, optionally preceded by a timestamp: {}", i + 1, line))),
+			};
+			let keycode_result = if name.len() > 1 && name.starts_with('_') {
+				Keycode::try_from(name).map_err(AlcError::from)
+			} else {
+				Keycode::try_from_display_name(name)
+			};
+			let keycode = match keycode_result {
+				Ok(v) => v,
+				Err(_e) => return Err(AlcError::KeystrokeLogError(path.to_path_buf(), format!("line {} is not a keycode: {}", i + 1, name))),
+			};
+			Self::add_from_stream(&mut all_ngram_to_counts, &mut recent, &[keycode]);
+			for ngram_to_counts in all_ngram_to_counts.iter_mut() {
+				ngram_to_counts.prune(max_ngrams_in_memory);
+			}
+		}
+		Ok(all_ngram_to_counts)
+	}
+
 	pub fn sum(&self) -> u32 {
 		self.frequencies.values().sum()
 	}
+}
+/// Reads (text n-gram, frequency) rows from a table exported by another analyzer. Frequencies can be counts or fractional values such as probabilities, but not negative. The format is chosen by extension:
+/// - .csv and .tsv: one row per line, with the frequency in the last column. Quoted n-grams ("a,") are unquoted and a header line is skipped
+/// - .json: either an object {"ab": 12} or an array of pairs [["ab", 0.5]]
+pub fn read_ngram_table<P>(filename: P) -> Result<Vec<(String, f64)>, AlcError> where P: AsRef<Path> {
+	let path = filename.as_ref();
+	let table_text = match std::fs::read_to_string(path) {
+		Ok(v) => v,
+		Err(e) => return Err(AlcError::GenericError(format!("{}, {:?}", e, path))),
+	};
+	let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+	parse_ngram_table(&table_text, &extension, path)
+}
 
-	pub fn len(&self) -> usize {
-		self.frequencies.len()
-	}
-	pub fn is_empty(&self) -> bool {
-		self.frequencies.is_empty()
+/// See [read_ngram_table]. `format` is "csv", "tsv", or "json", and `path` is only used in errors
+pub fn parse_ngram_table(table_text: &str, format: &str, path: &Path) -> Result<Vec<(String, f64)>, AlcError> {
+	match format {
+		"csv" | "tsv" => {
+			let separator = if format == "csv" { ',' } else { '\t' };
+			let mut rows = vec![];
+			for (i, line) in table_text.lines().enumerate() {
+				let line = line.strip_suffix('\r').unwrap_or(line);
+				if line.is_empty() {
+					continue;
+				}
+				let (text, count) = match line.rsplit_once(separator) {
+					Some(v) => v,
+					None => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("line {} has no count: {}", i + 1, line))),
+				};
+				let count = match count.trim().parse::<f64>() {
+					Ok(v) if v.is_finite() && v >= 0.0 => v,
+					Ok(v) => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("line {} has an invalid frequency: {}", i + 1, v))),
+					Err(_) if i == 0 => continue, // header
+					Err(e) => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("line {}: {}", i + 1, e))),
+				};
+				let text = match text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+					Some(quoted) => quoted.replace("\"\"", "\""),
+					None => text.to_string(),
+				};
+				rows.push((text, count));
+			}
+			Ok(rows)
+		},
+		"json" => {
+			let table: serde_json::Value = match serde_json::from_str(table_text) {
+				Ok(v) => v,
+				Err(e) => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("{}", e))),
+			};
+			let entries: Vec<(String, &serde_json::Value)> = match &table {
+				serde_json::Value::Object(map) => map.iter().map(|(k, v)| (k.clone(), v)).collect(),
+				serde_json::Value::Array(pairs) => {
+					let mut entries = vec![];
+					for pair in pairs {
+						match pair.as_array().map(|p| p.as_slice()) {
+							Some([serde_json::Value::String(text), count]) => entries.push((text.clone(), count)),
+							_ => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("expected [ngram, count], found {}", pair))),
+						}
+					}
+					entries
+				},
+				_ => return Err(AlcError::NgramTableError(path.to_path_buf(), String::from("expected an object or an array"))),
+			};
+			let mut rows = vec![];
+			for (text, count) in entries {
+				match count.as_f64().filter(|c| *c >= 0.0) {
+					Some(c) => rows.push((text, c)),
+					None => return Err(AlcError::NgramTableError(path.to_path_buf(), format!("frequency of {} is not a valid frequency: {}", text, count))),
+				}
+			}
+			Ok(rows)
+		},
+		_ => Err(AlcError::NgramTableError(path.to_path_buf(), String::from("expected a .csv, .tsv, or .json file"))),
 	}
 }
+
 impl<T> Index<Ngram> for SingleGramFrequencies<T> where T: Frequencies {
 	type Output = T;
 	fn index(&self, ngram: Ngram) -> &Self::Output {
@@ -178,6 +483,8 @@ impl<T> IntoIterator for SingleGramFrequencies<T> where T: Frequencies {
 
 #[cfg(test)]
 mod tests {
+	use std::fs;
+	use crate::text_processor::dataset::ExtractionOptions;
 	use super::*;
 	use Keycode::*;
 
@@ -216,7 +523,7 @@ mod tests {
 		let ngram = Ngram::new(vec![_A, _B]);
 		let mut expected_ngram_to_counts: HashMap<Ngram, u32> = HashMap::new();
 		expected_ngram_to_counts.insert(ngram, 1);
-		assert_eq!(SingleGramFrequencies::try_from_string("ab", 2, &KeycodeOptions::default()).unwrap().unwrap(), SingleGramFrequencies { frequencies: expected_ngram_to_counts, n: 2, total: 1.0 });
+		assert_eq!(SingleGramFrequencies::try_from_string("ab", 2, &KeycodeOptions::default()).unwrap().unwrap(), SingleGramFrequencies { frequencies: expected_ngram_to_counts, n: 2, total: 1.0, pruned_total: 0.0 });
 	}
 
 	#[test]
@@ -234,6 +541,125 @@ mod tests {
 		assert_eq!(holder4_val, 1)
 	}
 
+	#[test]
+	fn test_all_sizes_and_prune() {
+		let filename = std::env::temp_dir().join(format!("alc_test_all_sizes_{}.txt", std::process::id()));
+		fs::write(&filename, "abab\nabc\na\n").unwrap();
+		let all_sizes = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 4, &KeycodeOptions::default(), &ExtractionOptions::default()).unwrap();
+		assert_eq!(all_sizes.len(), 4);
+		for (i, holder) in all_sizes.iter().enumerate() {
+			assert_eq!(*holder, SingleGramFrequencies::<u32>::try_from_file(&filename, i + 1, &KeycodeOptions::default()).unwrap());
+		}
+		assert_eq!(all_sizes[1][Ngram::new(vec![_A, _B])], 3);
+		assert_eq!(all_sizes[3].len(), 1);
+
+		let mut pruned = all_sizes[1].clone();
+		pruned.prune(2);
+		assert_eq!(pruned.len(), 1);
+		assert_eq!(pruned[Ngram::new(vec![_A, _B])], 3);
+		assert_eq!(pruned.total, 3.0);
+		// the pruned "ba" and "bc" still count against coverage
+		assert_eq!(pruned.clone().take_top_frequencies(TopFrequenciesToTake::All), 0.6);
+		assert_eq!(pruned.clone().take_top_frequencies(TopFrequenciesToTake::Coverage(0.8)), 0.6);
+
+		let extraction_options = ExtractionOptions { max_ngrams_in_memory: 2, ..Default::default() };
+		let pruned_while_reading = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 2, &KeycodeOptions::default(), &extraction_options).unwrap();
+		assert!(pruned_while_reading[1].len() <= 2);
+		fs::remove_file(filename).unwrap();
+	}
+
+	#[test]
+	fn test_line_endings_and_words() {
+		let filename = std::env::temp_dir().join(format!("alc_test_line_endings_{}.txt", std::process::id()));
+		fs::write(&filename, "ab\ncd e\n").unwrap();
+		let options = KeycodeOptions::default();
+
+		let boundary = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 2, &options, &ExtractionOptions::default()).unwrap();
+		assert_eq!(boundary[1].get(&Ngram::new(vec![_B, _C])), None);
+		assert_eq!(boundary[0].get(&Ngram::new(vec![_ENT])), None);
+
+		let extraction_options = ExtractionOptions { line_endings: LineEndings::Enter, ..Default::default() };
+		let enter = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 3, &options, &extraction_options).unwrap();
+		assert_eq!(enter[0][Ngram::new(vec![_ENT])], 2);
+		assert_eq!(enter[1][Ngram::new(vec![_B, _ENT])], 1);
+		assert_eq!(enter[2][Ngram::new(vec![_B, _ENT, _C])], 1);
+		assert_eq!(enter[1][Ngram::new(vec![_E, _ENT])], 1);
+
+		let extraction_options = ExtractionOptions { line_endings: LineEndings::Space, ..Default::default() };
+		let space = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 3, &options, &extraction_options).unwrap();
+		assert_eq!(space[0][Ngram::new(vec![_SPC])], 3);
+		assert_eq!(space[2][Ngram::new(vec![_B, _SPC, _C])], 1);
+
+		let extraction_options = ExtractionOptions { line_endings: LineEndings::Enter, intra_word_only: true, ..Default::default() };
+		let words = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&filename, 2, &options, &extraction_options).unwrap();
+		assert_eq!(words[0].get(&Ngram::new(vec![_SPC])), None);
+		assert_eq!(words[1].get(&Ngram::new(vec![_D, _SPC])), None);
+		assert_eq!(words[1].get(&Ngram::new(vec![_D, _E])), None);
+		assert_eq!(words[1][Ngram::new(vec![_C, _D])], 1);
+		assert_eq!(words[0].total, 5.0);
+		fs::remove_file(filename).unwrap();
+	}
+
+	#[test]
+	fn test_ngram_table() {
+		let dir = std::env::temp_dir();
+		let text_filename = dir.join(format!("alc_test_ngram_table_{}.txt", std::process::id()));
+		fs::write(&text_filename, "aBa,b\n").unwrap();
+		let csv_filename = dir.join(format!("alc_test_ngram_table_{}.csv", std::process::id()));
+		fs::write(&csv_filename, "ngram,count\na,2\nB,1\n\",\",1\nb,1\naB,1\nBa,1\n\"a,\",1\n\",b\",1\naBa,1\n\"Ba,\",1\n\"a,b\",1\n").unwrap();
+		let json_filename = dir.join(format!("alc_test_ngram_table_{}.json", std::process::id()));
+		fs::write(&json_filename, r#"[["a", 2], ["B", 1], [",", 1], ["b", 1], ["aB", 1], ["Ba", 1], ["a,", 1], [",b", 1], ["aBa", 1], ["Ba,", 1], ["a,b", 1]]"#).unwrap();
+
+		let options = KeycodeOptions::default();
+		let extraction_options = ExtractionOptions::default();
+		let from_text = SingleGramFrequencies::<u32>::try_all_sizes_from_file(&text_filename, 3, &options, &extraction_options).unwrap();
+		let from_text = from_text.into_iter().map(|frequencies| frequencies.into_f64()).collect::<Vec<SingleGramFrequencies<f64>>>();
+		let from_csv = SingleGramFrequencies::<f64>::try_all_sizes_from_table(&csv_filename, 3, &options).unwrap();
+		let from_json = SingleGramFrequencies::<f64>::try_all_sizes_from_table(&json_filename, 3, &options).unwrap();
+		assert_eq!(from_csv, from_text);
+		assert_eq!(from_json, from_text);
+		assert_eq!(from_csv[1][Ngram::new(vec![_SFT, _B])], 1.0);
+
+		assert_eq!(parse_ngram_table("a\t0.25\nb\t1.5\n", "tsv", &text_filename).unwrap(), vec![(String::from("a"), 0.25), (String::from("b"), 1.5)]);
+		let probabilities = SingleGramFrequencies::<f64>::try_all_sizes_from_rows(parse_ngram_table(r#"{"a": 0.75, "b": 0.25}"#, "json", &json_filename).unwrap(), 1, &options).unwrap();
+		assert_eq!(probabilities[0][Ngram::new(vec![_A])], 0.75);
+		assert_eq!(probabilities[0].total, 1.0);
+		assert!(parse_ngram_table("a,-1\n", "csv", &csv_filename).is_err());
+		assert!(parse_ngram_table("a,NaN\n", "csv", &csv_filename).is_err());
+		assert!(parse_ngram_table(r#"[["a", -0.5]]"#, "json", &json_filename).is_err());
+		assert!(read_ngram_table(&text_filename).is_err());
+		for filename in [text_filename, csv_filename, json_filename] {
+			fs::remove_file(filename).unwrap();
+		}
+	}
+
+	#[test]
+	fn test_coverage() {
+		let mut holder = SingleGramFrequencies::<u32>::try_from_string("aaaaabbbcd", 1, &KeycodeOptions::default()).unwrap().unwrap();
+		assert_eq!(holder.clone().take_top_frequencies(Coverage(0.5)), 0.5);
+		assert_eq!(holder.clone().take_top_frequencies(Coverage(0.6)), 0.8);
+		assert_eq!(holder.clone().take_top_frequencies(Num(3)), 0.9);
+		assert_eq!(holder.take_top_frequencies(Coverage(0.85)), 0.9);
+		assert_eq!(holder.len(), 3);
+		assert_eq!(holder.total, 9.0);
+	}
+
+	#[test]
+	fn test_keystroke_log() {
+		let filename = std::env::temp_dir().join(format!("alc_test_keystroke_log_{}.keylog", std::process::id()));
+		fs::write(&filename, "# typing \"ab\" with a typo\n0.0 _A\n0.1 _C\n0.2 BSPC\n\n0.4 B\nLEFT\n").unwrap();
+		let all_sizes = SingleGramFrequencies::<u32>::try_all_sizes_from_keystroke_log(&filename, 3, 0).unwrap();
+		assert_eq!(all_sizes[0].len(), 5);
+		assert_eq!(all_sizes[0][Ngram::new(vec![_BSPC])], 1);
+		assert_eq!(all_sizes[1][Ngram::new(vec![_C, _BSPC])], 1);
+		assert_eq!(all_sizes[2][Ngram::new(vec![_BSPC, _B, _LEFT])], 1);
+		assert_eq!(all_sizes[2].total, 3.0);
+
+		fs::write(&filename, "0.0 _A\n_NOT_A_KEYCODE\n").unwrap();
+		assert!(SingleGramFrequencies::<u32>::try_all_sizes_from_keystroke_log(&filename, 3, 0).is_err());
+		fs::remove_file(filename).unwrap();
+	}
+
 	#[test]
 	fn test_read_from_file() {
 		let mut holder = SingleGramFrequencies::<u32>::try_from_file("./data/rust_book_test/ch04-02-references-and-borrowing.md", 2, &KeycodeOptions::default()).unwrap();
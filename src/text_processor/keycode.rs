@@ -11,6 +11,12 @@ pub struct KeycodeOptions {
 	pub include_misc_symbols: bool, // -/ etc.
 	pub include_misc_symbols_shifted: bool, // _? etc.
 	pub explicit_inclusions: Vec<Keycode>,
+	#[serde(default)]
+	pub shift_model: ShiftModel,
+	#[serde(default)]
+	pub shift_overrides: Vec<ShiftOverride>,
+	#[serde(default)]
+	pub character_keycodes: Vec<CharacterKeycodes>,
 }
 impl Default for KeycodeOptions {
 	fn default() -> Self {
@@ -22,6 +28,53 @@ impl Default for KeycodeOptions {
 			include_brackets: false,
 			include_misc_symbols_shifted: false,
 			explicit_inclusions: vec![_SPC, _SFT, _ENT, _TAB],
+			shift_model: ShiftModel::default(),
+			shift_overrides: vec![],
+			character_keycodes: vec![],
+		}
+	}
+}
+impl KeycodeOptions {
+	/// What shift + `k` types, taking shift overrides into account
+	pub fn shifted(&self, k: Keycode) -> Option<Keycode> {
+		match self.shift_overrides.iter().find(|o| o.key == k) {
+			Some(o) => Some(o.shifted),
+			None => k.default_shifted(),
+		}
+	}
+}
+
+/// How to type a character that isn't on a US-ANSI keyboard, e.g., é as its own keycode ([Keycode::_UC]), as a dead key followed by a letter ([Keycode::_DEAD]) or with AltGr ([Keycode::_ALTGR])
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CharacterKeycodes {
+	pub character: char,
+	pub keycodes: Vec<Keycode>,
+}
+
+/// Replaces what shift + `key` types, e.g., key = "_COMM" and shifted = "_SCLN" for boards where shift + comma types ;
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct ShiftOverride {
+	pub key: Keycode,
+	pub shifted: Keycode,
+}
+
+/// How shifted characters are typed
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, strum_macros::Display, Serialize, Deserialize)]
+pub enum ShiftModel {
+	/// _SFT is held while typing each shifted character
+	#[default]
+	Held,
+	/// One-shot shift (_OSS) is tapped before each shifted character
+	OneShot,
+	/// Caps word (_CAPW) is tapped once before a run of two or more capital letters and turns off at the end of the run. Other shifted characters use held _SFT
+	CapsWord,
+}
+impl ShiftModel {
+	/// Keycode typed before a single shifted character
+	pub fn shift_keycode(&self) -> Keycode {
+		match self {
+			ShiftModel::OneShot => _OSS,
+			_ => _SFT,
 		}
 	}
 }
@@ -68,6 +121,9 @@ pub enum Keycode {
 	_RBRC,
 	_LS(usize),
 	_LST(usize, usize),
+	_TG(usize),
+	_OSL(usize),
+	_LTAP(usize),
 	_UP,
 	_RGHT,
 	_DOWN,
@@ -79,6 +135,13 @@ pub enum Keycode {
 	_PSCR,
 	_DEL,
 	_TAB,
+	_OSS,
+	_CAPW,
+	_REP,
+	_MAGIC,
+	_ALTGR,
+	_DEAD(char),
+	_UC(char),
 	_PLACEHOLDER,
 }
 use Keycode::*;
@@ -123,6 +186,28 @@ pub fn generate_default_keycode_set(options: &KeycodeOptions) -> HashSet<Keycode
 			_UNDS, _PLUS, _PIPE, _COLN, _DQUO, _TILD, _QUES, _LCBR, _RCBR,
 		]));
 	}
+	match options.shift_model {
+		ShiftModel::Held => (),
+		ShiftModel::OneShot => { keycodes.insert(_OSS); },
+		ShiftModel::CapsWord => { keycodes.insert(_CAPW); },
+	}
+	// characters that can no longer be typed with shift need their own keycodes
+	for shift_override in options.shift_overrides.iter() {
+		if let Some(standalone) = shift_override.key.default_shifted() {
+			if keycodes.contains(&shift_override.key) && standalone != shift_override.shifted {
+				keycodes.insert(standalone);
+			}
+		}
+	}
+	// and characters that are now typed with shift don't need their own keycodes, unless they are shifted themselves
+	for shift_override in options.shift_overrides.iter() {
+		if !options.shift_overrides.iter().any(|o| o.key == shift_override.shifted) {
+			keycodes.remove(&shift_override.shifted);
+		}
+	}
+	for character_keycodes in options.character_keycodes.iter() {
+		keycodes.extend(character_keycodes.keycodes.iter().filter(|x| **x != _NO));
+	}
 	if !options.explicit_inclusions.is_empty() {
 		let ei = options.explicit_inclusions.iter().filter(|x| **x != _NO);
 		keycodes.extend(ei);
@@ -133,6 +218,49 @@ pub fn generate_default_keycode_set(options: &KeycodeOptions) -> HashSet<Keycode
 
 
 impl Keycode {
+	/// Layer reached by a layer switch (_LS), toggle (_TG), one-shot (_OSL) or layer-tap (_LTAP) key. Each of these has an _LST counterpart in the target layer
+	pub fn layer_target(&self) -> Option<usize> {
+		match self {
+			_LS(n) | _TG(n) | _OSL(n) | _LTAP(n) => Some(*n),
+			_ => None,
+		}
+	}
+	pub fn is_layer_switch(&self) -> bool {
+		self.layer_target().is_some()
+	}
+	/// How the keycode is written in the layout string, without the leading _. Characters outside of the regular keycodes (_UC) are written as themselves and dead keys as DK followed by the accent, e.g., é and DK´
+	pub fn display_name(&self) -> String {
+		match self {
+			_UC(c) => c.to_string(),
+			_DEAD(c) => format!("DK{}", c),
+			_ => str::replace(&self.to_string(), "_", ""),
+		}
+	}
+	/// Inverse of [Keycode::display_name]
+	pub fn try_from_display_name(name: &str) -> Result<Self, AlcError> {
+		let mut chars = name.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) if !c.is_ascii() => return Ok(_UC(c)),
+			_ => (),
+		}
+		if let Some(accent) = name.strip_prefix("DK") {
+			let mut accent_chars = accent.chars();
+			if let (Some(c), None) = (accent_chars.next(), accent_chars.next()) {
+				return Ok(_DEAD(c));
+			}
+		}
+		Ok(Keycode::try_from(format!("_{name}").as_str())?)
+	}
+	/// What shift + self types on a US-ANSI keyboard, for non-alpha keys
+	pub fn default_shifted(&self) -> Option<Keycode> {
+		let shift_pairs = [
+			(_1, _EXLM), (_2, _AT), (_3, _HASH), (_4, _DLR), (_5, _PERC),
+			(_6, _CIRC), (_7, _AMPR), (_8, _ASTR), (_9, _LPRN), (_ZERO, _RPRN),
+			(_MINS, _UNDS), (_EQL, _PLUS), (_BSLS, _PIPE), (_SCLN, _COLN), (_QUOT, _DQUO),
+			(_GRV, _TILD), (_SLSH, _QUES), (_COMM, _LT), (_DOT, _GT), (_LBRC, _LCBR), (_RBRC, _RCBR),
+		];
+		shift_pairs.iter().find(|(unshifted, _shifted)| unshifted == self).map(|(_unshifted, shifted)| *shifted)
+	}
 	fn to_char(self) -> Result<Option<char>, AlcError> {
 		let c = match self {
 			_SPC => ' ',
@@ -169,7 +297,10 @@ impl Keycode {
 			_RCBR => '}',
 			_LBRC => '[',
 			_RBRC => ']',
-			_SFT => return Ok(None),
+			_SFT | _OSS | _CAPW => return Ok(None),
+			_REP | _MAGIC => return Ok(None),
+			_ALTGR | _DEAD(_) => return Ok(None),
+			_UC(uc) => uc,
 			_TAB => return Ok(None),
 			_ => return Err(AlcError::GenericError(format!("Error for the developer! {} is not a valid explicit inclusion yet.", self))),
 		};
@@ -178,6 +309,7 @@ impl Keycode {
 
 	fn from_char(c: char, options: &KeycodeOptions) -> Result<Vec<Keycode>, AlcError> {
 		let mut keycodes: Vec<Keycode> = vec![];
+		let shift = options.shift_model.shift_keycode();
 		for inclusion in options.explicit_inclusions.clone() {
 			if let Some(k) = inclusion.to_char()? {
 				if k == c {
@@ -186,8 +318,29 @@ impl Keycode {
 				}
 			}
 		}
+		// user-defined characters come before the built-in ones, with shift in front for capitals that only have a lowercase entry
+		if let Some(character_keycodes) = options.character_keycodes.iter().find(|ck| ck.character == c) {
+			keycodes.extend(character_keycodes.keycodes.iter());
+			return Ok(keycodes);
+		}
+		if c.is_uppercase() {
+			let lowercase_c = c.to_lowercase().next().unwrap();
+			if let Some(character_keycodes) = options.character_keycodes.iter().find(|ck| ck.character == lowercase_c) {
+				keycodes.push(shift);
+				keycodes.extend(character_keycodes.keycodes.iter());
+				return Ok(keycodes);
+			}
+		}
+		// shift overrides come before the US-ANSI defaults below
+		for shift_override in options.shift_overrides.iter() {
+			if matches!(shift_override.shifted.to_char(), Ok(Some(shifted_c)) if shifted_c == c) {
+				keycodes.push(shift);
+				keycodes.push(shift_override.key);
+				return Ok(keycodes);
+			}
+		}
 		if c.is_uppercase() {
-			keycodes.push(_SFT);
+			keycodes.push(shift);
 		}
 		let c_to_test = c.to_lowercase().next().unwrap();
 
@@ -205,70 +358,70 @@ impl Keycode {
 					if options.include_number_symbols {
 						keycodes.push(_EXLM);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_1);
+						keycodes.push(shift); keycodes.push(_1);
 					}
 				},
 				'@' => {
 					if options.include_number_symbols {
 						keycodes.push(_AT);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_2);
+						keycodes.push(shift); keycodes.push(_2);
 					}
 				},
 				'#' => {
 					if options.include_number_symbols {
 						keycodes.push(_HASH);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_3);
+						keycodes.push(shift); keycodes.push(_3);
 					}
 				},
 				'$' => {
 					if options.include_number_symbols {
 						keycodes.push(_DLR);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_4);
+						keycodes.push(shift); keycodes.push(_4);
 					}
 				},
 				'%' => {
 					if options.include_number_symbols {
 						keycodes.push(_PERC);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_5);
+						keycodes.push(shift); keycodes.push(_5);
 					}
 				},
 				'^' => {
 					if options.include_number_symbols {
 						keycodes.push(_CIRC);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_6);
+						keycodes.push(shift); keycodes.push(_6);
 					}
 				},
 				'&' => {
 					if options.include_number_symbols {
 						keycodes.push(_AMPR);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_7);
+						keycodes.push(shift); keycodes.push(_7);
 					}
 				},
 				'*' => {
 					if options.include_number_symbols {
 						keycodes.push(_ASTR);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_8);
+						keycodes.push(shift); keycodes.push(_8);
 					}
 				},
 				'(' => {
 					if options.include_number_symbols || options.include_brackets {
 						keycodes.push(_LPRN);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_9);
+						keycodes.push(shift); keycodes.push(_9);
 					}
 				},
 				')' => {
 					if options.include_number_symbols || options.include_brackets {
 						keycodes.push(_RPRN);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_ZERO);
+						keycodes.push(shift); keycodes.push(_ZERO);
 					}
 				},
 				// _UNDS, _PLUS, _PIPE, _COLN, _DQUO, _TILD, _QUES,
@@ -276,49 +429,49 @@ impl Keycode {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_UNDS);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_MINS);
+						keycodes.push(shift); keycodes.push(_MINS);
 					}
 				},
 				'+' => {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_PLUS);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_EQL);
+						keycodes.push(shift); keycodes.push(_EQL);
 					}
 				},
 				'|' => {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_PIPE);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_BSLS);
+						keycodes.push(shift); keycodes.push(_BSLS);
 					}
 				},
 				':' => {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_COLN);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_SCLN);
+						keycodes.push(shift); keycodes.push(_SCLN);
 					}
 				},
 				'"' | '“' | '”' =>  {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_DQUO);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_QUOT);
+						keycodes.push(shift); keycodes.push(_QUOT);
 					}
 				},
 				'~' => {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_TILD);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_GRV);
+						keycodes.push(shift); keycodes.push(_GRV);
 					}
 				},
 				'?' => {
 					if options.include_misc_symbols_shifted {
 						keycodes.push(_QUES);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_SLSH);
+						keycodes.push(shift); keycodes.push(_SLSH);
 					}
 				}, 
 				'\'' => keycodes.push(_QUOT),
@@ -332,28 +485,28 @@ impl Keycode {
 					if options.include_brackets {
 						keycodes.push(_LT);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_COMM);
+						keycodes.push(shift); keycodes.push(_COMM);
 					}
 				}, 
 				'>' => {
 					if options.include_brackets {
 						keycodes.push(_GT);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_DOT);
+						keycodes.push(shift); keycodes.push(_DOT);
 					}
 				},
 				'{' => {
 					if options.include_brackets || options.include_misc_symbols_shifted {
 						keycodes.push(_LCBR);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_LBRC);
+						keycodes.push(shift); keycodes.push(_LBRC);
 					}
 				}, 
 				'}' => {
 					if options.include_brackets || options.include_misc_symbols_shifted {
 						keycodes.push(_RCBR);
 					} else {
-						keycodes.push(_SFT); keycodes.push(_RBRC);
+						keycodes.push(shift); keycodes.push(_RBRC);
 					}
 				}, 
 				'[' => keycodes.push(_LBRC),
@@ -372,13 +525,35 @@ impl Keycode {
 				},
 			}
 		}
+		// if shift + key was overridden to type something else, the character needs its own keycode instead
+		if let [k1, k2] = keycodes[..] {
+			if k1 == shift && options.shifted(k2) != k2.default_shifted() {
+				if let Some(standalone) = k2.default_shifted() {
+					return Ok(vec![standalone]);
+				}
+			}
+		}
 		Ok(keycodes)
 	}
 
 	pub fn from_string(s: &str, options: &KeycodeOptions) -> Result<Vec<Keycode>, AlcError> {
 		let mut keycodes: Vec<Keycode> = vec![];
-		for c in s.chars() {
-			keycodes.append(&mut Keycode::from_char(c, options)?);
+		let chars: Vec<char> = s.chars().collect();
+		let mut caps_word_active = false;
+		for (i, c) in chars.iter().enumerate() {
+			if options.shift_model == ShiftModel::CapsWord {
+				if !c.is_uppercase() {
+					caps_word_active = false;
+				} else if !caps_word_active && chars.get(i + 1).is_some_and(|next_c| next_c.is_uppercase()) {
+					caps_word_active = true;
+					keycodes.push(_CAPW);
+				}
+				if caps_word_active {
+					keycodes.append(&mut Keycode::from_char(c.to_lowercase().next().unwrap(), options)?);
+					continue;
+				}
+			}
+			keycodes.append(&mut Keycode::from_char(*c, options)?);
 		}
 		Ok(keycodes)
 	}
@@ -422,6 +597,62 @@ mod tests {
 		Ok(())
 	}
 
+	#[test]
+	fn test_shift_models() -> Result<(), AlcError> {
+		let mut options = KeycodeOptions::default();
+		assert_eq!(Keycode::from_string("HEy!", &options)?, vec![_SFT, _H, _SFT, _E, _Y, _SFT, _1]);
+		options.shift_model = ShiftModel::OneShot;
+		assert_eq!(Keycode::from_string("HEy!", &options)?, vec![_OSS, _H, _OSS, _E, _Y, _OSS, _1]);
+		options.shift_model = ShiftModel::CapsWord;
+		assert_eq!(Keycode::from_string("HEy I SAW", &options)?, vec![_CAPW, _H, _E, _Y, _SPC, _SFT, _I, _SPC, _CAPW, _S, _A, _W]);
+		assert!(generate_default_keycode_set(&options).contains(&_CAPW));
+		Ok(())
+	}
+
+	#[test]
+	fn test_shift_overrides() -> Result<(), AlcError> {
+		let mut options = KeycodeOptions::default();
+		assert_eq!(Keycode::from_string(";<", &options)?, vec![_SCLN, _SFT, _COMM]);
+		options.shift_overrides = vec![ShiftOverride { key: _COMM, shifted: _SCLN }, ShiftOverride { key: _DOT, shifted: _COLN }];
+		assert_eq!(options.shifted(_COMM), Some(_SCLN));
+		assert_eq!(options.shifted(_SLSH), Some(_QUES));
+		// ; and : are typed with shift, < and > no longer can be and need their own keycodes
+		assert_eq!(Keycode::from_string(";:<>?", &options)?, vec![_SFT, _COMM, _SFT, _DOT, _LT, _GT, _SFT, _SLSH]);
+		options.shift_model = ShiftModel::OneShot;
+		assert_eq!(Keycode::from_string(";", &options)?, vec![_OSS, _COMM]);
+		let keycodes = generate_default_keycode_set(&options);
+		assert!(keycodes.contains(&_LT) && keycodes.contains(&_GT));
+		assert!(!keycodes.contains(&_SCLN));
+		options.explicit_inclusions.push(_SCLN);
+		assert!(generate_default_keycode_set(&options).contains(&_SCLN));
+		Ok(())
+	}
+
+	#[test]
+	fn test_character_keycodes() -> Result<(), AlcError> {
+		let options = KeycodeOptions {
+			character_keycodes: vec![
+				CharacterKeycodes { character: 'ä', keycodes: vec![_UC('ä')] },
+				CharacterKeycodes { character: 'é', keycodes: vec![_DEAD('´'), _E] },
+				CharacterKeycodes { character: '€', keycodes: vec![_ALTGR, _E] },
+			],
+			..Default::default()
+		};
+		assert_eq!(Keycode::from_string("Bäé€", &options)?, vec![_SFT, _B, _UC('ä'), _DEAD('´'), _E, _ALTGR, _E]);
+		assert_eq!(Keycode::from_string("ÄÉ", &options)?, vec![_SFT, _UC('ä'), _SFT, _DEAD('´'), _E]);
+		let keycodes = generate_default_keycode_set(&options);
+		assert!(keycodes.contains(&_UC('ä')) && keycodes.contains(&_DEAD('´')) && keycodes.contains(&_ALTGR));
+
+		for k in [_UC('ä'), _DEAD('´'), _ALTGR, _SPC] {
+			assert_eq!(Keycode::try_from_display_name(&k.display_name())?, k);
+		}
+		assert_eq!(_UC('ä').display_name(), "ä");
+		assert_eq!(_DEAD('^').display_name(), "DK^");
+		let options_toml = toml::to_string(&options).unwrap();
+		assert_eq!(toml::from_str::<KeycodeOptions>(&options_toml).unwrap(), options);
+		Ok(())
+	}
+
 	#[test]
 	fn test_default_keycodes() {
 		let s = generate_default_keycode_set(&KeycodeOptions::default());
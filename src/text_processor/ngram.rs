@@ -6,7 +6,7 @@ use serde_derive::{Deserialize, Serialize};
 use super::keycode::Keycode;
 
 /// Holds a collection of keycodes corresponding to a string
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Deserialize, Serialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Deserialize, Serialize)]
 pub struct Ngram {
 	sequence: Vec<Keycode>,
 }
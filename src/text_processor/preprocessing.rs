@@ -0,0 +1,175 @@
+/// Rewrites source code toward what was actually typed, undoing what editors insert on their own
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct PreprocessingOptions {
+	pub strip_indentation: bool,
+	pub drop_auto_paired: bool, // closing brackets and closing double quotes
+	pub max_identifier_length: usize, // 0 means identifiers are kept whole
+	pub skip_comments: bool, // // and /* */ comments
+	pub skip_string_contents: bool, // text between double quotes, the quotes themselves are kept
+}
+impl PreprocessingOptions {
+	pub fn is_active(&self) -> bool {
+		*self != PreprocessingOptions::default()
+	}
+}
+
+/// Applies [PreprocessingOptions] line by line, remembering block comments and strings that span lines
+#[derive(Debug)]
+pub struct Preprocessor<'a> {
+	options: &'a PreprocessingOptions,
+	in_block_comment: bool,
+	in_string: bool,
+	raw_string_hashes: Option<usize>, // number of # around the raw string being read, r"" has 0
+}
+impl<'a> Preprocessor<'a> {
+	pub fn new(options: &'a PreprocessingOptions) -> Self {
+		Preprocessor { options, in_block_comment: false, in_string: false, raw_string_hashes: None }
+	}
+
+	pub fn process_line(&mut self, line: &str) -> String {
+		let options = self.options;
+		let chars: Vec<char> = if options.strip_indentation {
+			line.trim_start().chars().collect()
+		} else {
+			line.chars().collect()
+		};
+		let mut processed = String::with_capacity(line.len());
+		let mut identifier_length = 0;
+		let mut i = 0;
+		while i < chars.len() {
+			let c = chars[i];
+			let next_c = chars.get(i + 1).copied();
+			if self.in_block_comment {
+				if c == '*' && next_c == Some('/') {
+					self.in_block_comment = false;
+					i += 1;
+				}
+				i += 1;
+				continue;
+			}
+			if self.in_string {
+				if c == '\\' && self.raw_string_hashes.is_none() {
+					if !options.skip_string_contents {
+						processed.push(c);
+						processed.extend(next_c);
+					}
+					i += 2;
+					continue;
+				}
+				let hashes = self.raw_string_hashes.unwrap_or(0);
+				if c == '"' && chars.iter().skip(i + 1).take(hashes).filter(|h| **h == '#').count() == hashes {
+					self.in_string = false;
+					self.raw_string_hashes = None;
+					if !options.drop_auto_paired {
+						processed.push(c);
+					}
+					processed.extend(&chars[i + 1..i + 1 + hashes]);
+					i += 1 + hashes;
+				} else {
+					if !options.skip_string_contents {
+						processed.push(c);
+					}
+					i += 1;
+				}
+				continue;
+			}
+			if options.skip_comments && c == '/' {
+				if next_c == Some('/') {
+					break;
+				}
+				if next_c == Some('*') {
+					self.in_block_comment = true;
+					i += 2;
+					continue;
+				}
+			}
+			if c == '"' {
+				self.in_string = true;
+			}
+			// raw strings, r"..." or r#"..."#, also after b for byte strings
+			let previous_c = i.checked_sub(1).and_then(|j| chars.get(j)).copied();
+			if c == 'r' && (identifier_length == 0 || (identifier_length == 1 && previous_c == Some('b'))) {
+				let hashes = chars.iter().skip(i + 1).take_while(|h| **h == '#').count();
+				if chars.get(i + 1 + hashes) == Some(&'"') {
+					self.in_string = true;
+					self.raw_string_hashes = Some(hashes);
+					processed.extend(&chars[i..i + 2 + hashes]);
+					identifier_length = 0;
+					i += 2 + hashes;
+					continue;
+				}
+			}
+			// char literals, which can hold a quote or bracket without starting a string or being auto-paired. A ' that isn't closed right after is a lifetime or an apostrophe
+			if c == '\'' {
+				let literal_length = if next_c == Some('\\') {
+					chars.iter().skip(i + 3).take(9).position(|e| *e == '\'').map(|j| j + 4)
+				} else if chars.get(i + 2) == Some(&'\'') && next_c != Some('\'') {
+					Some(3)
+				} else {
+					None
+				};
+				if let Some(literal_length) = literal_length {
+					processed.extend(&chars[i..i + literal_length]);
+					identifier_length = 0;
+					i += literal_length;
+					continue;
+				}
+			}
+			if options.drop_auto_paired && matches!(c, ')' | ']' | '}') {
+				i += 1;
+				continue;
+			}
+			if c.is_alphabetic() || c == '_' || (identifier_length > 0 && c.is_alphanumeric()) {
+				identifier_length += 1;
+				if options.max_identifier_length > 0 && identifier_length > options.max_identifier_length {
+					i += 1;
+					continue;
+				}
+			} else {
+				identifier_length = 0;
+			}
+			processed.push(c);
+			i += 1;
+		}
+		processed
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_preprocessing() {
+		let source = "    let long_identifier = f(\"a \\\" b\"); // comment\n\t/* block\n  comment */ x[0]";
+		let process = |options: &PreprocessingOptions| {
+			let mut preprocessor = Preprocessor::new(options);
+			source.lines().map(|line| preprocessor.process_line(line)).collect::<Vec<String>>()
+		};
+
+		assert_eq!(process(&PreprocessingOptions::default()), source.lines().collect::<Vec<&str>>());
+		let options = PreprocessingOptions { strip_indentation: true, ..Default::default() };
+		assert_eq!(process(&options), vec!["let long_identifier = f(\"a \\\" b\"); // comment", "/* block", "comment */ x[0]"]);
+		let options = PreprocessingOptions { drop_auto_paired: true, skip_comments: true, ..Default::default() };
+		assert_eq!(process(&options), vec!["    let long_identifier = f(\"a \\\" b; ", "\t", " x[0"]);
+		let options = PreprocessingOptions { max_identifier_length: 4, skip_string_contents: true, ..Default::default() };
+		assert_eq!(process(&options), vec!["    let long = f(\"\"); // comm", "\t/* bloc", "  comm */ x[0]"]);
+		assert!(options.is_active());
+		assert!(!PreprocessingOptions::default().is_active());
+	}
+
+	#[test]
+	fn test_char_literals_and_strings() {
+		let source = "let q = '\"'; f(x)\nlet s = \"a (\nb\"; g(y)\nlet p = r\"\\\"; h(z)\nlet e = ['\\'', '(']; fn i<'a>(w: &'a str)";
+		let process = |options: &PreprocessingOptions| {
+			let mut preprocessor = Preprocessor::new(options);
+			source.lines().map(|line| preprocessor.process_line(line)).collect::<Vec<String>>()
+		};
+
+		assert_eq!(process(&PreprocessingOptions::default()), source.lines().collect::<Vec<&str>>());
+		let options = PreprocessingOptions { drop_auto_paired: true, ..Default::default() };
+		assert_eq!(process(&options), vec!["let q = '\"'; f(x", "let s = \"a (", "b; g(y", "let p = r\"\\; h(z", "let e = ['\\'', '('; fn i<'a>(w: &'a str"]);
+		let options = PreprocessingOptions { skip_string_contents: true, ..Default::default() };
+		assert_eq!(process(&options), vec!["let q = '\"'; f(x)", "let s = \"", "\"; g(y)", "let p = r\"\"; h(z)", "let e = ['\\'', '(']; fn i<'a>(w: &'a str)"]);
+	}
+}